@@ -0,0 +1,61 @@
+//! Serialization throughput of `Point::serialize`.
+//!
+//! Run with `cargo bench -p influxdb3-core --bench line_protocol`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use chrono::{TimeZone, Utc};
+use influxdb3_core::{NonFiniteFloatPolicy, Point, TagMap, TimestampPrecision};
+
+const POINTS: usize = 10_000;
+const RUN_FOR: Duration = Duration::from_secs(3);
+
+fn points() -> Vec<Point> {
+    let base = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+    (0..POINTS)
+        .map(|i| {
+            let mut p = Point::new_with_measurement("cpu load");
+            p.set_tag("host", &format!("server-{}", i % 100));
+            p.set_tag("region", "us west,2");
+            p.set_field("usage_user", i as f64 * 0.37);
+            p.set_field("usage_system", 1.0 / (i + 1) as f64);
+            p.set_field("processes", i as i64);
+            p.set_field("uptime", i as u64 * 1_000);
+            p.set_field("healthy", i % 2 == 0);
+            p.set_field("status", "running \"ok\"");
+            p.set_timestamp(base + chrono::Duration::nanoseconds(i as i64 * 1_337));
+            p
+        })
+        .collect()
+}
+
+fn main() {
+    let points = points();
+    let default_tags = TagMap::new();
+    let mut buf = Vec::with_capacity(4 * 1024 * 1024);
+
+    // Warm up caches and grow the buffer to its final size.
+    for p in &points {
+        p.serialize(&mut buf, TimestampPrecision::Nanoseconds, &default_tags, NonFiniteFloatPolicy::Reject).unwrap();
+    }
+
+    let start = Instant::now();
+    let mut serialized = 0;
+    let mut bytes = 0;
+    while start.elapsed() < RUN_FOR {
+        buf.clear();
+        for p in &points {
+            p.serialize(&mut buf, TimestampPrecision::Nanoseconds, &default_tags, NonFiniteFloatPolicy::Reject).unwrap();
+        }
+        bytes += black_box(&buf).len();
+        serialized += points.len();
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!(
+        "serialize: {:.0} points/sec, {:.1} MB/sec ({serialized} points in {elapsed:.2}s)",
+        serialized as f64 / elapsed,
+        bytes as f64 / elapsed / 1_000_000.0,
+    );
+}
@@ -1,61 +1,356 @@
-use crate::{InfluxDBError, TagMap, TimestampPrecision, ToPoint};
+use std::collections::VecDeque;
+use std::pin::Pin;
 
-const MAX_LINES: usize = 10_000;
-const MAX_BYTES: usize = 10 * 1024 * 1024; // 10 MB
+use futures::stream::{self, Stream, StreamExt as _};
+use tokio::io::{AsyncRead, AsyncReadExt as _};
 
-pub(crate) struct Batcher<'a> {
-    buffers: Vec<Vec<u8>>,
-    current_buffer: Vec<u8>,
-    current_lines: usize,
-    precision: TimestampPrecision,
-    default_tags: &'a TagMap,
+use crate::{InfluxDBError, ToPoint};
+use crate::line_protocol::{self, LineScanner};
+use crate::options::WriteConfig;
+
+pub(crate) const DEFAULT_MAX_LINES: usize = 10_000;
+pub(crate) const DEFAULT_MAX_BYTES: usize = 10 * 1024 * 1024; // 10 MB
+pub(crate) const DEFAULT_MAX_CONCURRENT_BATCHES: usize = 4;
+const READ_CHUNK: usize = 64 * 1024;
+
+/// A request body of serialized lines. Line `n` (1-based) of `data` is the
+/// point at input index `first_point + n - 1`.
+pub(crate) struct Batch {
+    pub first_point: usize,
+    pub lines: usize,
+    pub data: Vec<u8>,
+}
+
+impl Batch {
+    pub fn new(first_point: usize) -> Self {
+        Batch {
+            first_point,
+            lines: 0,
+            data: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines == 0
+    }
+}
+
+/// Splits serialized points into batches of at most `max_lines` lines and
+/// `max_bytes` bytes. Batches are only ever cut at line boundaries.
+pub(crate) struct Batcher {
+    ready: VecDeque<Batch>,
+    current_buffer: Batch,
 }
 
-impl<'a> Batcher<'a> {
-    pub fn new(precision: TimestampPrecision, default_tags: &'a TagMap) -> Self {
+impl Batcher {
+    pub fn new() -> Self {
         Batcher {
-            buffers: Vec::new(),
-            current_buffer: Vec::new(),
-            current_lines: 0,
-            precision,
-            default_tags,
+            ready: VecDeque::new(),
+            current_buffer: Batch::new(0),
         }
     }
 
-    pub fn add_point<T>(&mut self, point: T) -> Result<(), InfluxDBError>
+    pub fn add_point<T>(&mut self, point: T, config: &WriteConfig<'_>) -> Result<(), InfluxDBError>
     where
         T: ToPoint,
     {
-        point.to_point().serialize(&mut self.current_buffer, self.precision, self.default_tags);
-        self.current_lines += 1;
+        let start = self.current_buffer.data.len();
+        point.to_point().serialize(&mut self.current_buffer.data, config.precision, config.default_tags, config.float_policy)?;
+        self.end_line(start, config)
+    }
 
-        if self.current_lines >= MAX_LINES || self.current_buffer.len() >= MAX_BYTES {
-            let mut new_buffer = Vec::new();
-            std::mem::swap(&mut new_buffer, &mut self.current_buffer);
-            self.buffers.push(new_buffer);
-            self.current_lines = 0;
+    /// Adds a line of raw line protocol, without its newline. Blank lines
+    /// and comments are dropped.
+    pub fn add_line(&mut self, line: &[u8], config: &WriteConfig<'_>) -> Result<(), InfluxDBError> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if !line_protocol::is_data_line(line) {
+            return Ok(());
         }
-
-        Ok(())
+        let start = self.current_buffer.data.len();
+        self.current_buffer.data.extend_from_slice(line);
+        self.current_buffer.data.push(b'\n');
+        self.end_line(start, config)
     }
 
-    pub fn add_points<T, I>(&mut self, points: I) -> Result<(), InfluxDBError>
+    pub fn add_points<T, I>(&mut self, points: I, config: &WriteConfig<'_>) -> Result<(), InfluxDBError>
     where
         T: ToPoint,
         I: IntoIterator<Item = T>,
     {
         for point in points {
-            self.add_point(point)?;
+            self.add_point(point, config)?;
+        }
+        Ok(())
+    }
+
+    /// Takes the oldest batch that has reached one of the limits.
+    pub fn take_ready(&mut self) -> Option<Batch> {
+        self.ready.pop_front()
+    }
+
+    /// Takes the oldest batch, including the one still being filled.
+    pub fn take_any(&mut self) -> Option<Batch> {
+        if self.ready.is_empty() {
+            self.complete_current();
+        }
+        self.ready.pop_front()
+    }
+
+    pub fn has_ready(&self) -> bool {
+        !self.ready.is_empty()
+    }
+
+    /// Lines and bytes in the batch still being filled.
+    pub fn pending(&self) -> (usize, usize) {
+        (self.current_buffer.lines, self.current_buffer.data.len())
+    }
+
+    pub fn finalize(mut self) -> impl Iterator<Item = Batch> {
+        self.complete_current();
+        self.ready.into_iter()
+    }
+
+    /// Accounts for the line just appended at `start`, moving it to a new
+    /// batch if it doesn't fit in the current one.
+    fn end_line(&mut self, start: usize, config: &WriteConfig<'_>) -> Result<(), InfluxDBError> {
+        let line_len = self.current_buffer.data.len() - start;
+        if line_len > config.max_bytes {
+            self.current_buffer.data.truncate(start);
+            return Err(InfluxDBError::PointTooLarge(line_len, config.max_bytes));
+        }
+        if self.current_buffer.data.len() > config.max_bytes {
+            // The line doesn't fit: close the batch before it and start the
+            // next one with it.
+            let line = self.current_buffer.data.split_off(start);
+            self.complete_current();
+            self.current_buffer.data = line;
+        }
+        self.current_buffer.lines += 1;
+
+        if self.current_buffer.lines >= config.max_lines || self.current_buffer.data.len() >= config.max_bytes {
+            self.complete_current();
+        }
+
+        Ok(())
+    }
+
+    fn complete_current(&mut self) {
+        if self.current_buffer.is_empty() {
+            return;
+        }
+        let next = self.current_buffer.first_point + self.current_buffer.lines;
+        let full = std::mem::replace(&mut self.current_buffer, Batch::new(next));
+        self.ready.push_back(full);
+    }
+}
+
+/// Lazily serializes `points` into batches, holding at most one partially
+/// filled batch at a time.
+pub(crate) fn batch_stream<'a, S, T>(points: S, config: &'a WriteConfig<'a>) -> impl Stream<Item = Result<Batch, InfluxDBError>> + 'a
+where
+    S: Stream<Item = T> + 'a,
+    T: ToPoint,
+{
+    let state = Some((Box::pin(points), Batcher::new()));
+    stream::unfold(state, move |state| async move {
+        let (mut points, mut batcher) = state?;
+        loop {
+            if let Some(batch) = batcher.take_ready() {
+                return Some((Ok(batch), Some((points, batcher))));
+            }
+            match points.next().await {
+                Some(point) => {
+                    if let Err(e) = batcher.add_point(point, config) {
+                        return Some((Err(e), None));
+                    }
+                },
+                None => return batcher.take_any().map(|batch| (Ok(batch), None)),
+            }
+        }
+    })
+}
+
+struct ReaderState<R> {
+    reader: Pin<Box<R>>,
+    batcher: Batcher,
+    scanner: LineScanner,
+    pending: Vec<u8>,
+    scanned: usize,
+    eof: bool,
+}
+
+impl<R: AsyncRead> ReaderState<R> {
+    /// Reads the next chunk and moves the complete lines in it to the
+    /// batcher.
+    async fn fill(&mut self, config: &WriteConfig<'_>) -> Result<(), InfluxDBError> {
+        self.pending.reserve(READ_CHUNK);
+        if self.reader.read_buf(&mut self.pending).await? == 0 {
+            self.eof = true;
+            let last = std::mem::take(&mut self.pending);
+            return self.batcher.add_line(&last, config);
+        }
+
+        let mut line_start = 0;
+        while let Some(end) = self.scanner.find_line_end(&self.pending[self.scanned..]) {
+            let end = self.scanned + end;
+            self.batcher.add_line(&self.pending[line_start..end], config)?;
+            line_start = end + 1;
+            self.scanned = line_start;
+        }
+        self.pending.drain(..line_start);
+        self.scanned = self.pending.len();
+
+        if self.pending.len() > config.max_bytes {
+            return Err(InfluxDBError::PointTooLarge(self.pending.len(), config.max_bytes));
         }
         Ok(())
     }
+}
 
-    pub fn finalize(mut self) -> impl Iterator<Item = Vec<u8>> {
-        if !self.current_buffer.is_empty() {
-            let mut new_buffer = Vec::new();
-            std::mem::swap(&mut new_buffer, &mut self.current_buffer);
-            self.buffers.push(new_buffer);
+/// Lazily splits the line protocol read from `reader` into batches, holding
+/// at most one partially filled batch and one partial line at a time.
+pub(crate) fn batch_reader<'a, R>(reader: R, config: &'a WriteConfig<'a>) -> impl Stream<Item = Result<Batch, InfluxDBError>> + 'a
+where
+    R: AsyncRead + 'a,
+{
+    let state = ReaderState {
+        reader: Box::pin(reader),
+        batcher: Batcher::new(),
+        scanner: LineScanner::default(),
+        pending: Vec::new(),
+        scanned: 0,
+        eof: false,
+    };
+    stream::unfold(Some(state), move |state| async move {
+        let mut state = state?;
+        loop {
+            if let Some(batch) = state.batcher.take_ready() {
+                return Some((Ok(batch), Some(state)));
+            }
+            if state.eof {
+                return state.batcher.take_any().map(|batch| (Ok(batch), None));
+            }
+            if let Err(e) = state.fill(config).await {
+                return Some((Err(e), None));
+            }
         }
-        self.buffers.into_iter()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+    use crate::{NonFiniteFloatPolicy, Point, TagMap, TimestampPrecision};
+
+    fn config(tags: &TagMap, max_lines: usize, max_bytes: usize) -> WriteConfig<'_> {
+        WriteConfig {
+            database: "db",
+            precision: TimestampPrecision::Nanoseconds,
+            no_sync: false,
+            accept_partial: true,
+            default_tags: tags,
+            gzip_threshold: 0,
+            max_lines,
+            max_bytes,
+            max_concurrent_batches: 1,
+            float_policy: NonFiniteFloatPolicy::Reject,
+        }
+    }
+
+    /// `m v=<i>i <i>`, which is 9 bytes with its newline for `i` below 10.
+    fn point(i: i64) -> Point {
+        let mut p = Point::new_with_measurement("m");
+        p.set_field("v", i);
+        p.set_timestamp(DateTime::from_timestamp_nanos(i));
+        p
     }
-}
\ No newline at end of file
+
+    fn batches(batcher: Batcher) -> Vec<(usize, usize, String)> {
+        batcher.finalize()
+            .map(|b| (b.first_point, b.lines, String::from_utf8(b.data).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn splits_by_max_lines() {
+        let tags = TagMap::new();
+        let mut batcher = Batcher::new();
+        batcher.add_points((0..5).map(point), &config(&tags, 2, 1024)).unwrap();
+        assert_eq!(batches(batcher), [
+            (0, 2, "m v=0i 0\nm v=1i 1\n".to_string()),
+            (2, 2, "m v=2i 2\nm v=3i 3\n".to_string()),
+            (4, 1, "m v=4i 4\n".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn splits_by_max_bytes_at_line_boundaries() {
+        let tags = TagMap::new();
+        let mut batcher = Batcher::new();
+        batcher.add_points((0..5).map(point), &config(&tags, 100, 20)).unwrap();
+        assert_eq!(batches(batcher), [
+            (0, 2, "m v=0i 0\nm v=1i 1\n".to_string()),
+            (2, 2, "m v=2i 2\nm v=3i 3\n".to_string()),
+            (4, 1, "m v=4i 4\n".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn batch_filled_exactly_is_ready() {
+        let tags = TagMap::new();
+        let config = config(&tags, 100, 18);
+        let mut batcher = Batcher::new();
+        batcher.add_point(point(0), &config).unwrap();
+        assert!(!batcher.has_ready());
+        assert_eq!(batcher.pending(), (1, 9));
+        batcher.add_point(point(1), &config).unwrap();
+        assert!(batcher.has_ready());
+        assert_eq!(batcher.pending(), (0, 0));
+        assert_eq!(batcher.take_ready().unwrap().lines, 2);
+        assert!(batcher.take_ready().is_none());
+    }
+
+    #[test]
+    fn point_too_large_is_refused_and_leaves_the_batch_untouched() {
+        let tags = TagMap::new();
+        let config = config(&tags, 100, 10);
+        let mut batcher = Batcher::new();
+        batcher.add_point(point(1), &config).unwrap();
+
+        let mut big = point(2);
+        big.set_field("s", "x".repeat(20));
+        assert!(matches!(batcher.add_point(big, &config), Err(InfluxDBError::PointTooLarge(_, 10))));
+        assert_eq!(batcher.pending(), (1, 9));
+
+        // The refused point does not take up an index.
+        batcher.add_point(point(3), &config).unwrap();
+        assert_eq!(batches(batcher), [
+            (0, 1, "m v=1i 1\n".to_string()),
+            (1, 1, "m v=3i 3\n".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn raw_lines_skip_blanks_and_comments() {
+        let tags = TagMap::new();
+        let config = config(&tags, 2, 1024);
+        let mut batcher = Batcher::new();
+        for line in ["m v=1i 1\r", "", "  # comment", "m v=2i 2", "m v=3i 3"] {
+            batcher.add_line(line.as_bytes(), &config).unwrap();
+        }
+        assert_eq!(batches(batcher), [
+            (0, 2, "m v=1i 1\nm v=2i 2\n".to_string()),
+            (2, 1, "m v=3i 3\n".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn raw_line_too_large_is_refused() {
+        let tags = TagMap::new();
+        let mut batcher = Batcher::new();
+        let err = batcher.add_line(b"m v=1i 1700000000000000000", &config(&tags, 100, 10));
+        assert!(matches!(err, Err(InfluxDBError::PointTooLarge(27, 10))));
+        assert_eq!(batcher.pending(), (0, 0));
+    }
+}
@@ -1,31 +1,59 @@
-use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
 use async_compression::tokio::write::GzipEncoder;
-use futures::StreamExt as _;
+use bytes::Bytes;
+use futures::{Stream, StreamExt as _};
 use url::Url;
 use reqwest::Client as HttpClient;
 use reqwest::{header, StatusCode};
-use tokio::io::AsyncWriteExt as _;
+use tokio::io::{AsyncRead, AsyncWriteExt as _};
 use arrow_flight::Ticket;
-use arrow_flight::decode::FlightRecordBatchStream;
+use arrow_flight::decode::FlightDataDecoder;
 use arrow_flight::flight_service_client::FlightServiceClient;
 use serde::{Deserialize, Serialize};
 use futures::stream::{BoxStream, TryStreamExt as _};
 
-use crate::{ClientBuilder, FromPoint, InfluxDBError, Point, PointStream, TagMap, TimestampPrecision, ToPoint, batch_writer};
+use crate::{ClientBuilder, DecodeColumn, DrainReport, Encode, FlightSqlClient, FromRecordBatch, InfluxDBError, NonFiniteFloatPolicy, PointStream, QueryOptions, QueryType, RecordBatchStream, RetryPolicy, TagMap, TimestampPrecision, ToPoint, WriteApi, WriteApiOptions, WriteOptions};
+use crate::options::WriteConfig;
+use crate::spool::Spool;
+use crate::batch_writer::{self, Batch};
+use crate::error::{LineError, PartialWriteError};
+use crate::record_batch_stream::QueryLimits;
 
+#[derive(Clone)]
 pub struct Client {
     pub(crate) api_url: Url,
 
     pub(crate) gzip_threshold: usize,
     pub(crate) no_sync: bool,
+    pub(crate) accept_partial: bool,
     pub(crate) precision: TimestampPrecision,
+    pub(crate) float_policy: NonFiniteFloatPolicy,
     pub(crate) org: String,
     pub(crate) database: String,
+    pub(crate) default_tags: TagMap,
+    pub(crate) max_batch_lines: usize,
+    pub(crate) max_batch_bytes: usize,
+    pub(crate) timeout: Duration,
+    pub(crate) query_timeout: Option<Duration>,
 
     pub(crate) http_client: HttpClient,
     pub(crate) flight_client: FlightServiceClient<tonic::transport::Channel>,
     pub(crate) authorization: String,
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) spool: Option<Arc<Spool>>,
+}
+
+/// Everything besides the body needed to send a batch, so it can be replayed
+/// from the spool.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct WriteRequest {
+    pub database: String,
+    pub precision: TimestampPrecision,
+    pub no_sync: bool,
+    pub accept_partial: bool,
+    pub gzip: bool,
 }
 
 impl Client {
@@ -33,91 +61,478 @@ impl Client {
         ClientBuilder::default()
     }
 
-    pub async fn query(&self, query: &str) -> Result<BoxStream<'_, Result<Point, InfluxDBError>>, InfluxDBError> {
-        Ok(_query(self, query, None).await?.boxed())
+    /// A Flight SQL client on the same connection, for prepared statements
+    /// and the standard metadata calls.
+    pub fn flight_sql(&self) -> FlightSqlClient {
+        FlightSqlClient::new(self)
+    }
+
+    /// Runs a SQL query and converts each row to a [`Point`](crate::Point).
+    /// [`PointStream::result_schema`] tells tag, field and time columns apart.
+    pub async fn query(&self, query: &str) -> Result<PointStream, InfluxDBError> {
+        _query_points(self, query, QueryType::SQL, None, &QueryOptions::default()).await
     }
 
-    pub async fn query_as<T>(&self, query: &str) -> Result<BoxStream<'_, Result<T, InfluxDBError>>, InfluxDBError>
+    /// Runs a SQL query and converts the rows with [`FromRecordBatch`]. A
+    /// type that only implements [`FromPoint`](crate::FromPoint) can be read
+    /// as [`ViaPoint<T>`](crate::ViaPoint).
+    pub async fn query_as<'a, T>(&'a self, query: &str) -> Result<BoxStream<'a, Result<T, InfluxDBError>>, InfluxDBError>
     where
-        T: FromPoint,
+        T: FromRecordBatch + Send + 'a,
     {
         Ok(
-            _query(self, query, None).await?
-                .map(|p| {
-                    p.and_then(|p| T::from_point(p))
-                }).boxed()
+            rows(_query(self, query, QueryType::SQL, None, &QueryOptions::default()).await?)
         )
     }
 
-    pub async fn query_with_params(&self, query: &str, params: HashMap<&str, &str>) -> Result<BoxStream<'_, Result<Point, InfluxDBError>>, InfluxDBError> {
-        Ok(_query(self, query, Some(params)).await?.boxed())
+    /// Runs a SQL query with the limits in `options`.
+    pub async fn query_with_options(&self, query: &str, options: &QueryOptions) -> Result<PointStream, InfluxDBError> {
+        _query_points(self, query, QueryType::SQL, None, options).await
+    }
+
+    pub async fn query_as_with_options<'a, T>(&'a self, query: &str, options: &QueryOptions) -> Result<BoxStream<'a, Result<T, InfluxDBError>>, InfluxDBError>
+    where
+        T: FromRecordBatch + Send + 'a,
+    {
+        Ok(rows(_query(self, query, QueryType::SQL, None, options).await?))
+    }
+
+    /// Runs a SQL query and collects every row.
+    pub async fn query_all<T>(&self, query: &str) -> Result<Vec<T>, InfluxDBError>
+    where
+        T: FromRecordBatch + Send,
+    {
+        self.query_as(query).await?.try_collect().await
+    }
+
+    /// Runs a SQL query that must return exactly one row.
+    pub async fn query_one<T>(&self, query: &str) -> Result<T, InfluxDBError>
+    where
+        T: FromRecordBatch + Send,
+    {
+        self.query_optional(query).await?.ok_or(InfluxDBError::NoRows)
+    }
+
+    /// Runs a SQL query that returns at most one row.
+    pub async fn query_optional<T>(&self, query: &str) -> Result<Option<T>, InfluxDBError>
+    where
+        T: FromRecordBatch + Send,
+    {
+        single(self.query_as(query).await?).await
+    }
+
+    /// Runs a SQL query that returns a single value, e.g.
+    /// `SELECT count(*) FROM cpu`. A null decodes to `None` if `T` is an
+    /// `Option` and is [`InfluxDBError::NullValue`] otherwise.
+    pub async fn query_scalar<T>(&self, query: &str) -> Result<T, InfluxDBError>
+    where
+        T: DecodeColumn + Send,
+    {
+        scalar(self.query_arrow(query).await?).await
+    }
+
+    /// [`query_all`](Client::query_all) with `$name` placeholders bound to
+    /// `params`.
+    pub async fn query_all_with_params<T, P, K, V>(&self, query: &str, params: P) -> Result<Vec<T>, InfluxDBError>
+    where
+        T: FromRecordBatch + Send,
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Encode,
+    {
+        self.query_with_params_as(query, params).await?.try_collect().await
+    }
+
+    /// [`query_one`](Client::query_one) with `$name` placeholders bound to
+    /// `params`.
+    pub async fn query_one_with_params<T, P, K, V>(&self, query: &str, params: P) -> Result<T, InfluxDBError>
+    where
+        T: FromRecordBatch + Send,
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Encode,
+    {
+        self.query_optional_with_params(query, params).await?.ok_or(InfluxDBError::NoRows)
+    }
+
+    /// [`query_optional`](Client::query_optional) with `$name` placeholders
+    /// bound to `params`.
+    pub async fn query_optional_with_params<T, P, K, V>(&self, query: &str, params: P) -> Result<Option<T>, InfluxDBError>
+    where
+        T: FromRecordBatch + Send,
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Encode,
+    {
+        single(self.query_with_params_as(query, params).await?).await
     }
 
-    pub async fn query_with_params_as<T>(&self, query: &str, params: HashMap<&str, &str>) -> Result<BoxStream<'_, Result<T, InfluxDBError>>, InfluxDBError>
+    /// [`query_scalar`](Client::query_scalar) with `$name` placeholders bound
+    /// to `params`.
+    pub async fn query_scalar_with_params<T, P, K, V>(&self, query: &str, params: P) -> Result<T, InfluxDBError>
     where
-        T: FromPoint,
+        T: DecodeColumn + Send,
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Encode,
+    {
+        scalar(self.query_arrow_with_params(query, params).await?).await
+    }
+
+    /// Runs a SQL query and returns the results as Arrow record batches,
+    /// without converting them to points.
+    pub async fn query_arrow(&self, query: &str) -> Result<RecordBatchStream, InfluxDBError> {
+        _query(self, query, QueryType::SQL, None, &QueryOptions::default()).await
+    }
+
+    pub async fn query_arrow_with_options(&self, query: &str, options: &QueryOptions) -> Result<RecordBatchStream, InfluxDBError> {
+        _query(self, query, QueryType::SQL, None, options).await
+    }
+
+    pub async fn query_arrow_with_params<P, K, V>(&self, query: &str, params: P) -> Result<RecordBatchStream, InfluxDBError>
+    where
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Encode,
+    {
+        _query(self, query, QueryType::SQL, Some(encode_params(params)?), &QueryOptions::default()).await
+    }
+
+    /// Runs an InfluxQL query. Rows map onto points the same way SQL results
+    /// do, with the measurement taken from the `iox::measurement` column.
+    pub async fn query_influxql(&self, query: &str) -> Result<PointStream, InfluxDBError> {
+        _query_points(self, query, QueryType::InfluxQL, None, &QueryOptions::default()).await
+    }
+
+    pub async fn query_influxql_as<'a, T>(&'a self, query: &str) -> Result<BoxStream<'a, Result<T, InfluxDBError>>, InfluxDBError>
+    where
+        T: FromRecordBatch + Send + 'a,
     {
         Ok(
-            _query(self, query, Some(params)).await?
-                .map(|p| {
-                    p.and_then(|p| T::from_point(p))
-                }).boxed()
+            rows(_query(self, query, QueryType::InfluxQL, None, &QueryOptions::default()).await?)
         )
     }
 
+    /// Runs a SQL query with `$name` placeholders bound to `params`. Values
+    /// keep their type, so they compare against columns without casts.
+    /// Timestamps are sent as RFC 3339 strings and decimals as strings with
+    /// every digit.
+    pub async fn query_with_params<P, K, V>(&self, query: &str, params: P) -> Result<PointStream, InfluxDBError>
+    where
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Encode,
+    {
+        _query_points(self, query, QueryType::SQL, Some(encode_params(params)?), &QueryOptions::default()).await
+    }
+
+    pub async fn query_with_params_as<'a, T, P, K, V>(&'a self, query: &str, params: P) -> Result<BoxStream<'a, Result<T, InfluxDBError>>, InfluxDBError>
+    where
+        T: FromRecordBatch + Send + 'a,
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Encode,
+    {
+        Ok(
+            rows(_query(self, query, QueryType::SQL, Some(encode_params(params)?), &QueryOptions::default()).await?)
+        )
+    }
+
+    /// Runs an InfluxQL query with `$name` placeholders bound to `params`,
+    /// typed the same way as in [`query_with_params`](Client::query_with_params).
+    pub async fn query_influxql_with_params<P, K, V>(&self, query: &str, params: P) -> Result<PointStream, InfluxDBError>
+    where
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Encode,
+    {
+        _query_points(self, query, QueryType::InfluxQL, Some(encode_params(params)?), &QueryOptions::default()).await
+    }
+
+    pub async fn query_influxql_with_params_as<'a, T, P, K, V>(&'a self, query: &str, params: P) -> Result<BoxStream<'a, Result<T, InfluxDBError>>, InfluxDBError>
+    where
+        T: FromRecordBatch + Send + 'a,
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Encode,
+    {
+        Ok(
+            rows(_query(self, query, QueryType::InfluxQL, Some(encode_params(params)?), &QueryOptions::default()).await?)
+        )
+    }
+
+    /// Starts a background writer that batches points sent to it. See
+    /// [`WriteApi`].
+    pub fn write_api(&self, options: WriteApiOptions) -> WriteApi {
+        WriteApi::new(self.clone(), options)
+    }
+
     pub async fn write_points<I, T>(&self, points: I) -> Result<(), InfluxDBError>
     where
         T: ToPoint,
         I: IntoIterator<Item = T>,
     {
-        self.write_points_with_tags(points, &HashMap::new()).await
+        self.write_points_with_options(points, &WriteOptions::default()).await
     }
 
+    /// Writes points with `default_tags` added to the client's default tags.
+    /// Where both have a tag, the one in `default_tags` is used.
     pub async fn write_points_with_tags<I, T>(&self, points: I, default_tags: &TagMap) -> Result<(), InfluxDBError>
     where
         T: ToPoint,
         I: IntoIterator<Item = T>,
     {
-        let mut batcher = batch_writer::Batcher::new(self.precision, default_tags);
-        batcher.add_points(points)?;
+        let mut tags = self.default_tags.clone();
+        tags.extend(default_tags.iter().map(|(k, v)| (k.clone(), v.clone())));
+        let options = WriteOptions::default().default_tags(tags);
+        self.write_points_with_options(points, &options).await
+    }
+
+    pub async fn write_points_with_options<I, T>(&self, points: I, options: &WriteOptions) -> Result<(), InfluxDBError>
+    where
+        T: ToPoint,
+        I: IntoIterator<Item = T>,
+    {
+        let config = self.write_config(options);
+        let mut batcher = batch_writer::Batcher::new();
+        batcher.add_points(points, &config)?;
+
+        // Lines the server rejects do not stop the write: the rest of the batch
+        // was accepted, so the remaining batches are still sent and all the
+        // rejections are reported together. Without `accept_partial` the
+        // rejected batch was dropped as a whole, so stop there instead.
+        let mut partial: Option<PartialWriteError> = None;
+        for batch in batcher.finalize() {
+            collect_partial(&mut partial, self.send_batch(batch, &config).await, &config)?;
+        }
+
+        match partial {
+            Some(e) => Err(InfluxDBError::PartialWrite(e)),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn write_stream<S, T>(&self, points: S) -> Result<(), InfluxDBError>
+    where
+        T: ToPoint,
+        S: Stream<Item = T>,
+    {
+        self.write_stream_with_options(points, &WriteOptions::default()).await
+    }
+
+    /// Writes points as they are produced by `points`. Points are serialized
+    /// only once there is room for another batch, and up to
+    /// `max_concurrent_batches` batches are uploaded at the same time, so
+    /// memory use does not grow with the length of the stream.
+    ///
+    /// Batches may be accepted by the server out of order. Rejected lines are
+    /// collected the same way as in
+    /// [`write_points_with_options`](Client::write_points_with_options), and
+    /// any other error stops the write, cancelling the uploads in flight.
+    pub async fn write_stream_with_options<S, T>(&self, points: S, options: &WriteOptions) -> Result<(), InfluxDBError>
+    where
+        T: ToPoint,
+        S: Stream<Item = T>,
+    {
+        let config = self.write_config(options);
+        self.upload(batch_writer::batch_stream(points, &config), &config).await
+    }
+
+    /// Writes line protocol that is already serialized, such as a file
+    /// written by Telegraf.
+    pub async fn write_lp<B>(&self, lp: B) -> Result<(), InfluxDBError>
+    where
+        B: AsRef<[u8]>,
+    {
+        self.write_lp_with_options(lp, &WriteOptions::default()).await
+    }
+
+    pub async fn write_lp_with_options<B>(&self, lp: B, options: &WriteOptions) -> Result<(), InfluxDBError>
+    where
+        B: AsRef<[u8]>,
+    {
+        self.write_lp_reader_with_options(lp.as_ref(), options).await
+    }
+
+    pub async fn write_lp_reader<R>(&self, reader: R) -> Result<(), InfluxDBError>
+    where
+        R: AsyncRead,
+    {
+        self.write_lp_reader_with_options(reader, &WriteOptions::default()).await
+    }
+
+    /// Writes line protocol read from `reader`. The input is split into
+    /// batches at line boundaries and uploaded like
+    /// [`write_stream_with_options`](Client::write_stream_with_options) does.
+    ///
+    /// Lines are sent as they are, so the precision in `options` has to
+    /// match their timestamps and default tags are not added. Blank lines
+    /// and comments are dropped, and the point indexes of rejected lines
+    /// count the remaining lines from zero.
+    pub async fn write_lp_reader_with_options<R>(&self, reader: R, options: &WriteOptions) -> Result<(), InfluxDBError>
+    where
+        R: AsyncRead,
+    {
+        let config = self.write_config(options);
+        self.upload(batch_writer::batch_reader(reader, &config), &config).await
+    }
+
+    async fn upload<S>(&self, batches: S, config: &WriteConfig<'_>) -> Result<(), InfluxDBError>
+    where
+        S: Stream<Item = Result<Batch, InfluxDBError>>,
+    {
+        let uploads = batches
+            .map(|batch| async move { self.send_batch(batch?, config).await })
+            .buffer_unordered(config.max_concurrent_batches);
+        let mut uploads = std::pin::pin!(uploads);
+
+        let mut partial: Option<PartialWriteError> = None;
+        while let Some(result) = uploads.next().await {
+            collect_partial(&mut partial, result, config)?;
+        }
+
+        match partial {
+            Some(mut e) => {
+                e.lines.sort_by_key(|line| (line.point_index.is_none(), line.point_index));
+                Err(InfluxDBError::PartialWrite(e))
+            },
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn write_config<'a>(&'a self, options: &'a WriteOptions) -> WriteConfig<'a> {
+        WriteConfig {
+            database: options.database.as_deref().unwrap_or(&self.database),
+            precision: options.precision.unwrap_or(self.precision),
+            no_sync: options.no_sync.unwrap_or(self.no_sync),
+            accept_partial: options.accept_partial.unwrap_or(self.accept_partial),
+            default_tags: options.default_tags.as_ref().unwrap_or(&self.default_tags),
+            gzip_threshold: options.gzip_threshold.unwrap_or(self.gzip_threshold),
+            max_lines: options.max_lines.unwrap_or(self.max_batch_lines),
+            max_bytes: options.max_bytes.unwrap_or(self.max_batch_bytes),
+            max_concurrent_batches: options.max_concurrent_batches.unwrap_or(batch_writer::DEFAULT_MAX_CONCURRENT_BATCHES),
+            float_policy: self.float_policy,
+        }
+    }
+
+    pub(crate) async fn send_batch(&self, batch: Batch, config: &WriteConfig<'_>) -> Result<(), InfluxDBError> {
+        let first_point = batch.first_point;
+        let mut buf = batch.data;
+        let gzip = config.gzip_threshold > 0 && buf.len() > config.gzip_threshold;
+        if gzip {
+            let mut encoder = GzipEncoder::new(Vec::new()); 
+            encoder.write_all(&buf).await?;
+            encoder.shutdown().await?;
+            buf = encoder.into_inner();
+        }
+
+        let body = Bytes::from(buf);
+        let request = WriteRequest {
+            database: config.database.to_string(),
+            precision: config.precision,
+            no_sync: config.no_sync,
+            accept_partial: config.accept_partial,
+            gzip,
+        };
+
+        let Some(spool) = &self.spool else {
+            return self.retry_policy.run(|| self.post_write(body.clone(), &request, first_point)).await;
+        };
+
+        // Anything already spooled has to reach the server before this batch,
+        // so while the server is unreachable this one joins the queue.
+        if let Err(e) = self.send_spooled(spool).await {
+            if !self.retry_policy.is_retryable(&e) {
+                return Err(e);
+            }
+            return spool.append(&request, body).await.map_err(|_| e);
+        }
+        match self.retry_policy.run(|| self.post_write(body.clone(), &request, first_point)).await {
+            Err(e) if self.retry_policy.is_retryable(&e) => spool.append(&request, body).await.map_err(|_| e),
+            result => result,
+        }
+    }
+
+    /// Sends the batches held in the write spool, oldest first. Stops at the
+    /// first retryable error (see [`RetryPolicy`]), leaving the rest on disk.
+    /// A batch refused with any other error would be refused again, so it is
+    /// dropped, as are lines the server rejects; the report says why. Does
+    /// nothing if the client has no spool.
+    pub async fn drain_spool(&self) -> Result<DrainReport, InfluxDBError> {
+        let Some(spool) = &self.spool else {
+            return Ok(DrainReport::default());
+        };
+        let sent = self.send_spooled(spool).await?;
+        Ok(DrainReport { sent, dropped: spool.take_dropped() })
+    }
+
+    /// Sends the spooled batches and returns how many were sent. What is
+    /// dropped is kept for the next `drain_spool`.
+    async fn send_spooled(&self, spool: &Arc<Spool>) -> Result<usize, InfluxDBError> {
+        if !spool.has_pending() {
+            return Ok(0);
+        }
+
+        let _guard = spool.drain_lock.lock().await;
+        let mut sent = 0;
+        while let Some(record) = spool.peek().await? {
+            match self.post_write(record.body.clone(), &record.request, 0).await {
+                Ok(()) => sent += 1,
+                Err(e @ InfluxDBError::PartialWrite(_)) => {
+                    sent += 1;
+                    spool.record_dropped(e);
+                },
+                Err(e) if self.retry_policy.is_retryable(&e) => return Err(e),
+                Err(e) => spool.record_dropped(e),
+            }
+            spool.advance(&record).await?;
+        }
+        Ok(sent)
+    }
 
+    async fn post_write(&self, body: Bytes, request: &WriteRequest, first_point: usize) -> Result<(), InfluxDBError> {
         let uri = self.api_url.join("/api/v3/write_lp")?;
         let mut params = vec![];
-        let headers = header::HeaderMap::new();
+        let mut headers = header::HeaderMap::new();
 
         params.push(("org", self.org.as_str()));
-        params.push(("db", self.database.as_str()));
-        params.push(("precision", self.precision.v2_str()));
-        params.push(("no_sync", if self.no_sync { "true" } else { "false" }));
-        
-
-        for mut buf in batcher.finalize() {
-            let mut headers = headers.clone();
-            if self.gzip_threshold > 0 && buf.len() > self.gzip_threshold {
-                let mut encoder = GzipEncoder::new(Vec::new()); 
-                encoder.write_all(&buf).await?;
-                encoder.shutdown().await?;
-                buf = encoder.into_inner();
-                headers.insert(header::CONTENT_ENCODING, "gzip".parse().unwrap());
-            }
+        params.push(("db", request.database.as_str()));
+        params.push(("precision", request.precision.v2_str()));
+        params.push(("no_sync", if request.no_sync { "true" } else { "false" }));
+        params.push(("accept_partial", if request.accept_partial { "true" } else { "false" }));
 
-            let req = self.http_client.post(uri.clone())
-                .headers(headers)
-                .query(&params)
-                .body(buf)
-                .send()
-                .await?;
+        if request.gzip {
+            headers.insert(header::CONTENT_ENCODING, "gzip".parse().unwrap());
+        }
 
-            if self.no_sync && req.status() == StatusCode::METHOD_NOT_ALLOWED {
-                return Err(InfluxDBError::V3NotSupported);
-            }
+        let req = self.http_client.post(uri)
+            .headers(headers)
+            .query(&params)
+            .body(body)
+            .send()
+            .await?;
 
-            if !req.status().is_success() {
-                return handle_http_err(req).await;
-            }
+        if request.no_sync && req.status() == StatusCode::METHOD_NOT_ALLOWED {
+            return Err(InfluxDBError::V3NotSupported);
         }
 
-        Ok(())
+        handle_http_err(req, first_point).await
+    }
+}
+
+/// Folds rejected lines from one batch into `partial`. Any other error, or a
+/// rejection when partial writes are disabled, is returned.
+fn collect_partial(partial: &mut Option<PartialWriteError>, result: Result<(), InfluxDBError>, config: &WriteConfig<'_>) -> Result<(), InfluxDBError> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(InfluxDBError::PartialWrite(e)) if config.accept_partial => {
+            match partial {
+                Some(partial) => partial.lines.extend(e.lines),
+                None => *partial = Some(e),
+            }
+            Ok(())
+        },
+        Err(e) => Err(e),
     }
 }
 
@@ -125,6 +540,26 @@ impl Client {
 pub(crate) struct ErrorInternal {
     #[serde(default)]
     pub error_message: String,
+    #[serde(default)]
+    pub original_line: String,
+    #[serde(default)]
+    pub line_number: Option<usize>,
+}
+
+/// `data` is a single object for most errors and a list of rejected lines
+/// for partial writes.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ErrorData {
+    Lines(Vec<ErrorInternal>),
+    Line(ErrorInternal),
+    Other(serde::de::IgnoredAny),
+}
+
+impl Default for ErrorData {
+    fn default() -> Self {
+        ErrorData::Line(ErrorInternal::default())
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -136,7 +571,7 @@ pub(crate) struct ServerError {
     #[serde(default)]
     pub error: String,
     #[serde(default)]
-    pub data: ErrorInternal,
+    pub data: ErrorData,
 }
 
 #[derive(Debug, Serialize)]
@@ -145,10 +580,31 @@ struct TicketData<'a> {
     sql_query: &'a str,
     query_type: &'a str,
     #[serde(skip_serializing_if = "Option::is_none")]
-    params: Option<HashMap<&'a str, &'a str>>,
+    params: Option<QueryParams>,
 }
 
-async fn handle_http_err(resp: reqwest::Response) -> Result<(), InfluxDBError> {
+type QueryParams = serde_json::Map<String, serde_json::Value>;
+
+fn encode_params<P, K, V>(params: P) -> Result<QueryParams, InfluxDBError>
+where
+    P: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: Encode,
+{
+    params.into_iter()
+        .map(|(name, value)| {
+            let name = name.as_ref();
+            let value = value.encode();
+            let json = value.to_json()
+                .ok_or_else(|| InfluxDBError::InvalidParameter(name.to_string(), format!("{value:?}")))?;
+            Ok((name.to_string(), json))
+        })
+        .collect()
+}
+
+/// `first_point` is the input index of the first line in the request body,
+/// used to map rejected lines back to the points that produced them.
+async fn handle_http_err(resp: reqwest::Response, first_point: usize) -> Result<(), InfluxDBError> {
     if resp.status().is_success() {
         Ok(())
     } else {
@@ -158,15 +614,35 @@ async fn handle_http_err(resp: reqwest::Response) -> Result<(), InfluxDBError> {
         }
 
         let status = resp.status();
-        let mut message;
+        let mut message = String::new();
         let content_type = resp.headers().get(header::CONTENT_TYPE)
             .and_then(|v| v.to_str().ok()).unwrap_or("");
         if content_type.starts_with("application/json") {
             let error: ServerError = resp.json().await?;
-            if error.message.is_empty() && error.code.is_empty() {
-                message = error.data.error_message;
-            } else {
-                message = error.error;
+            let lines = match error.data {
+                ErrorData::Lines(lines) => lines,
+                ErrorData::Line(line) if line.line_number.is_some() => vec![line],
+                ErrorData::Line(line) if error.message.is_empty() && error.code.is_empty() && !line.error_message.is_empty() => {
+                    message = line.error_message;
+                    vec![]
+                },
+                _ => {
+                    message = error.error.clone();
+                    vec![]
+                },
+            };
+            if !lines.is_empty() {
+                return Err(InfluxDBError::PartialWrite(PartialWriteError {
+                    message: error.error,
+                    lines: lines.into_iter()
+                        .map(|line| LineError {
+                            point_index: line.line_number.and_then(|n| n.checked_sub(1)).map(|i| first_point + i),
+                            line_number: line.line_number,
+                            original_line: line.original_line,
+                            message: line.error_message,
+                        })
+                        .collect(),
+                }));
             }
         } else {
             message = resp.text().await.unwrap_or_default();
@@ -174,15 +650,18 @@ async fn handle_http_err(resp: reqwest::Response) -> Result<(), InfluxDBError> {
         if message.is_empty() {
             message = format!("HTTP error: {}", status);
         }
+        if status.is_server_error() {
+            return Err(InfluxDBError::ServerError(status.as_u16(), message));
+        }
         Err(InfluxDBError::ApiError(message))
     }
 }
 
-async fn _query(client: &Client, query: &str, params: Option<HashMap<&str, &str>>) -> Result<PointStream, InfluxDBError> {
+async fn _query(client: &Client, query: &str, query_type: QueryType, params: Option<QueryParams>, options: &QueryOptions) -> Result<RecordBatchStream, InfluxDBError> {
     let ticket_data = TicketData {
         database: &client.database,
         sql_query: query,
-        query_type: "sql",
+        query_type: query_type.as_str(),
         params,
     };
     let ticket_json = serde_json::to_vec(&ticket_data)?;
@@ -190,8 +669,254 @@ async fn _query(client: &Client, query: &str, params: Option<HashMap<&str, &str>
     let mut request = tonic::Request::new(ticket);
     request.metadata_mut().insert("authorization", client.authorization.parse().unwrap());
 
-    let stream = client.flight_client.clone().do_get(request).await?.into_inner();
-    let reader = FlightRecordBatchStream::new_from_flight_data(stream.map_err(|e| e.into()));
+    let mut limits = QueryLimits::new(options, client.query_timeout);
+    let mut flight_client = client.flight_client.clone();
+    let response = limits.start(client.timeout, async { Ok(flight_client.do_get(request).await?) }).await?;
+    let decoder = FlightDataDecoder::new(response.into_inner().map_err(|e| e.into()));
 
-    Ok(PointStream::new(reader))
-}
\ No newline at end of file
+    RecordBatchStream::with_limits(decoder, limits).await
+}
+
+async fn _query_points(client: &Client, query: &str, query_type: QueryType, params: Option<QueryParams>, options: &QueryOptions) -> Result<PointStream, InfluxDBError> {
+    Ok(PointStream::new(_query(client, query, query_type, params, options).await?))
+}
+
+/// The only item of `rows`, if there is one. Reads at most two items.
+async fn single<T>(mut rows: BoxStream<'_, Result<T, InfluxDBError>>) -> Result<Option<T>, InfluxDBError> {
+    let Some(first) = rows.try_next().await? else {
+        return Ok(None);
+    };
+    if rows.try_next().await?.is_some() {
+        return Err(InfluxDBError::TooManyRows);
+    }
+    Ok(Some(first))
+}
+
+/// The only value of the only column of `batches`.
+async fn scalar<T>(batches: RecordBatchStream) -> Result<T, InfluxDBError>
+where
+    T: DecodeColumn + Send,
+{
+    let columns = batches.schema().fields().len();
+    if columns != 1 {
+        return Err(InfluxDBError::UnexpectedColumnCount(columns));
+    }
+    let name = batches.schema().field(0).name().clone();
+
+    let values = batches
+        .map_ok(|batch| futures::stream::iter(T::decode_column(batch.column(0).as_ref(), &name)))
+        .try_flatten()
+        .boxed();
+    single(values).await?
+        .ok_or(InfluxDBError::NoRows)?
+        .ok_or(InfluxDBError::NullValue(name))
+}
+
+/// Converts query results a batch at a time with
+/// [`FromRecordBatch::from_record_batch`]. The rows of a batch are held until
+/// they are read, which is why `T` has to be `Send`.
+fn rows<'a, T>(batches: RecordBatchStream) -> BoxStream<'a, Result<T, InfluxDBError>>
+where
+    T: FromRecordBatch + Send + 'a,
+{
+    batches
+        .map_ok(|batch| futures::stream::iter(T::from_record_batch(&batch)))
+        .try_flatten()
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone as _, Utc};
+    use serde_json::json;
+    use crate::PointValue;
+
+    fn response(status: u16, json: &str) -> reqwest::Response {
+        http::Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(json.to_string())
+            .unwrap()
+            .into()
+    }
+
+    async fn partial_write(json: &str, first_point: usize) -> PartialWriteError {
+        match handle_http_err(response(400, json), first_point).await {
+            Err(InfluxDBError::PartialWrite(e)) => e,
+            other => panic!("expected a partial write error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejected_lines_from_a_list() {
+        let e = partial_write(r#"{
+            "error": "partial write of line protocol occurred",
+            "data": [
+                {"original_line": "m v=x", "line_number": 2, "error_message": "invalid field value"},
+                {"original_line": "m", "line_number": 5, "error_message": "no fields"}
+            ]
+        }"#, 10).await;
+        assert_eq!(e.message, "partial write of line protocol occurred");
+        let lines: Vec<_> = e.lines.iter()
+            .map(|l| (l.point_index, l.line_number, l.original_line.as_str(), l.message.as_str()))
+            .collect();
+        assert_eq!(lines, [
+            (Some(11), Some(2), "m v=x", "invalid field value"),
+            (Some(14), Some(5), "m", "no fields"),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn rejected_line_from_a_single_object() {
+        let e = partial_write(r#"{
+            "error": "parsing failed",
+            "data": {"original_line": "m v=x", "line_number": 1, "error_message": "invalid field value"}
+        }"#, 0).await;
+        assert_eq!(e.message, "parsing failed");
+        assert_eq!(e.lines.len(), 1);
+        assert_eq!((e.lines[0].point_index, e.lines[0].line_number), (Some(0), Some(1)));
+    }
+
+    #[tokio::test]
+    async fn rejected_lines_without_a_line_number_have_no_point_index() {
+        let e = partial_write(r#"{
+            "error": "partial write of line protocol occurred",
+            "data": [
+                {"original_line": "m v=x", "error_message": "invalid field value"},
+                {"original_line": "m", "line_number": 0, "error_message": "no fields"}
+            ]
+        }"#, 10).await;
+        assert!(e.lines.iter().all(|l| l.point_index.is_none()));
+        assert_eq!(e.lines[1].line_number, Some(0));
+    }
+
+    #[tokio::test]
+    async fn error_without_lines() {
+        let err = handle_http_err(response(400, r#"{"error": "database not found", "data": null}"#), 0).await;
+        assert!(matches!(err, Err(InfluxDBError::ApiError(message)) if message == "database not found"));
+
+        let err = handle_http_err(response(400, r#"{"data": {"error_message": "bad precision"}}"#), 0).await;
+        assert!(matches!(err, Err(InfluxDBError::ApiError(message)) if message == "bad precision"));
+
+        let err = handle_http_err(response(503, r#"{"error": "unavailable"}"#), 0).await;
+        assert!(matches!(err, Err(InfluxDBError::ServerError(503, message)) if message == "unavailable"));
+    }
+
+    fn ticket(query_type: QueryType, params: Option<QueryParams>) -> serde_json::Value {
+        let ticket = TicketData { database: "db", sql_query: "SELECT 1", query_type: query_type.as_str(), params };
+        serde_json::to_value(&ticket).unwrap()
+    }
+
+    #[test]
+    fn ticket_without_params() {
+        assert_eq!(ticket(QueryType::SQL, None), json!({
+            "database": "db",
+            "sql_query": "SELECT 1",
+            "query_type": "sql",
+        }));
+        assert_eq!(ticket(QueryType::InfluxQL, None)["query_type"], "influxql");
+    }
+
+    #[test]
+    fn ticket_params_keep_their_type() {
+        let time = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let params = encode_params([
+            ("null", PointValue::Null),
+            ("float", PointValue::Float(1.5)),
+            ("integer", PointValue::Integer(-42)),
+            ("uinteger", PointValue::UInteger(u64::MAX)),
+            ("boolean", PointValue::Boolean(true)),
+            ("string", PointValue::String("it's".to_string())),
+            ("time", PointValue::Timestamp(time)),
+            ("time_ns", PointValue::Timestamp(time + chrono::Duration::nanoseconds(123_456_789))),
+            ("decimal", PointValue::Decimal(12345, 2)),
+            ("wide_decimal", PointValue::Decimal(12_345_678_901_234_567_890_123, 4)),
+        ]).unwrap();
+        assert_eq!(ticket(QueryType::SQL, Some(params))["params"], json!({
+            "null": null,
+            "float": 1.5,
+            "integer": -42,
+            "uinteger": u64::MAX,
+            "boolean": true,
+            "string": "it's",
+            "time": "2024-06-01T12:00:00Z",
+            "time_ns": "2024-06-01T12:00:00.123456789Z",
+            "decimal": "123.45",
+            "wide_decimal": "1234567890123456789.0123",
+        }));
+    }
+
+    #[test]
+    fn params_without_a_json_form_are_refused() {
+        for value in [
+            PointValue::Float(f64::NAN),
+            PointValue::Float(f64::INFINITY),
+            PointValue::Interval(1, 2, 3),
+            PointValue::Binary(vec![1]),
+        ] {
+            let err = encode_params([("p", value.clone())]).unwrap_err();
+            assert!(matches!(&err, InfluxDBError::InvalidParameter(name, _) if name == "p"), "{value:?}: {err:?}");
+        }
+    }
+
+    fn rows(items: Vec<Result<i64, InfluxDBError>>) -> BoxStream<'static, Result<i64, InfluxDBError>> {
+        futures::stream::iter(items).boxed()
+    }
+
+    #[tokio::test]
+    async fn single_row() {
+        assert_eq!(single(rows(vec![])).await.unwrap(), None);
+        assert_eq!(single(rows(vec![Ok(1)])).await.unwrap(), Some(1));
+        assert!(matches!(single(rows(vec![Ok(1), Ok(2)])).await, Err(InfluxDBError::TooManyRows)));
+        assert!(matches!(single(rows(vec![Err(InfluxDBError::QueryTimeout)])).await, Err(InfluxDBError::QueryTimeout)));
+        assert!(matches!(single(rows(vec![Ok(1), Err(InfluxDBError::QueryTimeout)])).await, Err(InfluxDBError::QueryTimeout)));
+    }
+
+    /// Results with the given batches, sent through Flight the way queries
+    /// answer.
+    async fn batches(batches: Vec<arrow_array::RecordBatch>) -> RecordBatchStream {
+        let flight_data = arrow_flight::encode::FlightDataEncoderBuilder::new()
+            .build(futures::stream::iter(batches.into_iter().map(Ok)));
+        RecordBatchStream::new(FlightDataDecoder::new(flight_data)).await.unwrap()
+    }
+
+    fn count(values: Vec<Option<i64>>) -> arrow_array::RecordBatch {
+        let column: arrow_array::ArrayRef = Arc::new(arrow_array::Int64Array::from(values));
+        arrow_array::RecordBatch::try_from_iter([("count", column)]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn scalar_value() {
+        assert_eq!(scalar::<i64>(batches(vec![count(vec![Some(3)])]).await).await.unwrap(), 3);
+        assert_eq!(scalar::<i64>(batches(vec![count(vec![]), count(vec![Some(3)])]).await).await.unwrap(), 3);
+        assert_eq!(scalar::<Option<i64>>(batches(vec![count(vec![None])]).await).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn scalar_null_is_an_error() {
+        let err = scalar::<i64>(batches(vec![count(vec![None])]).await).await.unwrap_err();
+        assert!(matches!(&err, InfluxDBError::NullValue(name) if name == "count"), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn scalar_needs_one_row() {
+        let err = scalar::<i64>(batches(vec![count(vec![])]).await).await.unwrap_err();
+        assert!(matches!(err, InfluxDBError::NoRows), "{err:?}");
+        let err = scalar::<i64>(batches(vec![]).await).await.unwrap_err();
+        assert!(matches!(err, InfluxDBError::UnexpectedColumnCount(0)), "{err:?}");
+
+        let err = scalar::<i64>(batches(vec![count(vec![Some(1), Some(2)])]).await).await.unwrap_err();
+        assert!(matches!(err, InfluxDBError::TooManyRows), "{err:?}");
+        let err = scalar::<i64>(batches(vec![count(vec![Some(1)]), count(vec![Some(2)])]).await).await.unwrap_err();
+        assert!(matches!(err, InfluxDBError::TooManyRows), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn scalar_needs_one_column() {
+        let column: arrow_array::ArrayRef = Arc::new(arrow_array::Int64Array::from(vec![1]));
+        let batch = arrow_array::RecordBatch::try_from_iter([("a", column.clone()), ("b", column)]).unwrap();
+        let err = scalar::<i64>(batches(vec![batch]).await).await.unwrap_err();
+        assert!(matches!(err, InfluxDBError::UnexpectedColumnCount(2)), "{err:?}");
+    }
+}
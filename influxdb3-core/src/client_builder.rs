@@ -5,7 +5,11 @@ use url::Url;
 use reqwest::ClientBuilder as ReqwestClientBuilder;
 use tonic::transport::Endpoint;
 
-use crate::{InfluxDBError, TimestampPrecision, Client};
+use std::sync::Arc;
+
+use crate::{InfluxDBError, NonFiniteFloatPolicy, RetryPolicy, SpoolOptions, TagMap, TimestampPrecision, Client};
+use crate::batch_writer;
+use crate::spool::Spool;
 
 const USER_AGENT: &str = "influxdb3-rs/0.1";
 
@@ -31,8 +35,15 @@ pub struct ClientBuilder {
     ssl_root_certificates: Option<String>,
     proxy: Option<Url>,
     precision: TimestampPrecision,
+    float_policy: NonFiniteFloatPolicy,
     gzip_threshold: usize,
     no_sync: bool,
+    accept_partial: bool,
+    default_tags: TagMap,
+    max_batch_lines: usize,
+    max_batch_bytes: usize,
+    retry_policy: RetryPolicy,
+    spool: Option<SpoolOptions>,
 }
 
 impl Default for ClientBuilder {
@@ -54,8 +65,15 @@ impl Default for ClientBuilder {
             ssl_root_certificates: None,
             proxy: None,
             precision: TimestampPrecision::Nanoseconds,
+            float_policy: NonFiniteFloatPolicy::Reject,
             gzip_threshold: 1024,
             no_sync: false,
+            accept_partial: true,
+            default_tags: TagMap::new(),
+            max_batch_lines: batch_writer::DEFAULT_MAX_LINES,
+            max_batch_bytes: batch_writer::DEFAULT_MAX_BYTES,
+            retry_policy: RetryPolicy::default(),
+            spool: None,
         }
     }
 }
@@ -68,8 +86,10 @@ impl ClientBuilder {
             return Err(InfluxDBError::InvalidScheme(uri.scheme().to_string()));
         }
 
-        let mut client = ClientBuilder::default();
-        client.host = format!("{}://{}", uri.scheme(), uri.host_str().unwrap()).parse()?;
+        let mut client = ClientBuilder {
+            host: format!("{}://{}", uri.scheme(), uri.host_str().unwrap()).parse()?,
+            ..Default::default()
+        };
 
         for (k, v) in uri.query_pairs() {
             match k.as_ref() {
@@ -122,6 +142,11 @@ impl ClientBuilder {
         self
     }
 
+    pub fn non_finite_float_policy(mut self, float_policy: NonFiniteFloatPolicy) -> Self {
+        self.float_policy = float_policy;
+        self
+    }
+
     pub fn gzip_threshold(mut self, gzip_threshold: usize) -> Self {
         self.gzip_threshold = gzip_threshold;
         self
@@ -132,11 +157,57 @@ impl ClientBuilder {
         self
     }
 
+    /// Whether the server should keep the valid lines of a request that also
+    /// contains invalid ones. Defaults to `true`.
+    pub fn accept_partial(mut self, accept_partial: bool) -> Self {
+        self.accept_partial = accept_partial;
+        self
+    }
+
+    /// Tags added to every written point.
+    pub fn default_tags(mut self, default_tags: TagMap) -> Self {
+        self.default_tags = default_tags;
+        self
+    }
+
+    /// Maximum number of lines sent in one write request.
+    pub fn max_batch_lines(mut self, max_batch_lines: usize) -> Self {
+        self.max_batch_lines = max_batch_lines.max(1);
+        self
+    }
+
+    /// Maximum size of one write request body before compression. Writing a
+    /// point whose line alone is larger fails with
+    /// [`InfluxDBError::PointTooLarge`].
+    pub fn max_batch_bytes(mut self, max_batch_bytes: usize) -> Self {
+        self.max_batch_bytes = max_batch_bytes.max(1);
+        self
+    }
+
+    /// How failed writes are retried. Each batch of a write is retried on its
+    /// own, so batches that were already accepted are not sent again.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Keeps batches that could not be delivered in an on-disk spool and
+    /// sends them once the server is reachable again. See [`SpoolOptions`].
+    pub fn spool(mut self, spool: SpoolOptions) -> Self {
+        self.spool = Some(spool);
+        self
+    }
+
+    /// Timeout for HTTP requests and for connecting to the server. gRPC calls
+    /// that don't return query results, and queries without a deadline
+    /// waiting for the server to start answering, also get this long.
     pub fn timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
         self
     }
 
+    /// Default for [`QueryOptions::timeout`](crate::QueryOptions::timeout):
+    /// how long a whole query, including reading its results, may take.
     pub fn query_timeout(mut self, query_timeout: Duration) -> Self {
         self.query_timeout = Some(query_timeout);
         self
@@ -180,9 +251,12 @@ impl ClientBuilder {
         let authorization = format!("{} {}", self.auth_scheme, self.token);
         self.default_headers.insert("Authorization", authorization.parse().unwrap());
 
+        // A timeout on the channel would also cut off queries given a longer
+        // deadline in `QueryOptions`. Every gRPC call goes through
+        // `QueryLimits::start` instead, which falls back to `self.timeout`,
+        // so the channel itself only times out connecting.
         let mut endpoint = Endpoint::from_shared(self.host.to_string())?
                 .user_agent(USER_AGENT)?
-                .timeout(self.timeout)
                 .connect_timeout(self.timeout)
                 .concurrency_limit(self.max_idle_connections);
 
@@ -193,10 +267,6 @@ impl ClientBuilder {
             .default_headers(self.default_headers)
             .gzip(true);
 
-        if let Some(query_timeout) = self.query_timeout {
-            endpoint = endpoint.timeout(query_timeout);
-        }
-        
         if let Some(proxy_url) = self.proxy {
             http_builder = http_builder.proxy(reqwest::Proxy::all(proxy_url.as_str())?);
         }
@@ -219,13 +289,22 @@ impl ClientBuilder {
 
             gzip_threshold: self.gzip_threshold,
             no_sync: self.no_sync,
+            accept_partial: self.accept_partial,
             precision: self.precision,
+            float_policy: self.float_policy,
             org: self.organization,
             database: self.database,
+            default_tags: self.default_tags,
+            max_batch_lines: self.max_batch_lines,
+            max_batch_bytes: self.max_batch_bytes,
+            timeout: self.timeout,
+            query_timeout: self.query_timeout,
 
             http_client: http_builder.build()?,
             flight_client: FlightServiceClient::new(endpoint.connect_lazy()),
             authorization,
+            retry_policy: self.retry_policy,
+            spool: self.spool.map(Spool::open).transpose()?.map(Arc::new),
         })
     }
 }
\ No newline at end of file
@@ -5,6 +5,9 @@ pub enum InfluxDBError {
     #[error("Flight Error: {0}")]
     FlightError(#[from] arrow_flight::error::FlightError),
 
+    #[error("Arrow error: {0}")]
+    ArrowError(#[from] arrow_schema::ArrowError),
+
     #[error("Json error: {0}")]
     JsonError(#[from] serde_json::Error),
 
@@ -17,6 +20,12 @@ pub enum InfluxDBError {
     #[error("API error: {0}")]
     ApiError(String),
 
+    #[error("Server error ({0}): {1}")]
+    ServerError(u16, String),
+
+    #[error("Partial write: {0}")]
+    PartialWrite(PartialWriteError),
+
     #[error("Invalid URI: {0}")]
     InvalidUri(#[from] url::ParseError),
 
@@ -56,9 +65,106 @@ pub enum InfluxDBError {
     #[error("Invalid point value conversion: {0}")]
     InvalidPointValueConversion(String),
 
+    #[error("Invalid point: {0}")]
+    InvalidPoint(String),
+
+    #[error("Line protocol parse error: {0}")]
+    Parse(ParseError),
+
+    #[error("Line is {0} bytes, more than the batch limit of {1} bytes")]
+    PointTooLarge(usize, usize),
+
     #[error("Invalid point value type received: {0} {1}")]
     InvalidPointValue(String, String),
 
+    #[error("Write spool is full")]
+    SpoolFull,
+
+    #[error("Write API has been closed")]
+    WriteApiClosed,
+
+    #[error("Query returned no rows")]
+    NoRows,
+
+    #[error("Query returned more than one row")]
+    TooManyRows,
+
+    #[error("Expected a single column, query returned {0}")]
+    UnexpectedColumnCount(usize),
+
+    #[error("Query returned null in column {0}")]
+    NullValue(String),
+
+    #[error("Query timed out")]
+    QueryTimeout,
+
+    #[error("Query was cancelled")]
+    QueryCancelled,
+
+    #[error("Query returned more than {0} rows")]
+    RowLimitExceeded(usize),
+
+    #[error("Query returned more than {0} bytes")]
+    ByteLimitExceeded(usize),
+
+    #[error("Export error: {0}")]
+    ExportError(String),
+
     #[error("Other error: {0}")]
     Other(String),
-}
\ No newline at end of file
+}
+
+impl InfluxDBError {
+    /// Whether the error is likely to go away if the request is repeated:
+    /// rate limiting, 5xx responses other than 501/505, and connection
+    /// failures or timeouts.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            InfluxDBError::RateLimited(_) => true,
+            InfluxDBError::ServerError(status, _) => !matches!(status, 501 | 505),
+            InfluxDBError::ReqwestError(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+/// Lines the server refused during a write.
+#[derive(Debug, Clone)]
+pub struct PartialWriteError {
+    pub message: String,
+    pub lines: Vec<LineError>,
+}
+
+/// A single line rejected by the server.
+#[derive(Debug, Clone)]
+pub struct LineError {
+    /// Index of the point in the input of the write call that produced the
+    /// line. `None` if the server did not say which line it rejected.
+    pub point_index: Option<usize>,
+    /// Line number within the request body, starting at 1, if the server
+    /// reported one.
+    pub line_number: Option<usize>,
+    pub original_line: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for PartialWriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({} lines rejected)", self.message, self.lines.len())
+    }
+}
+
+/// Line protocol that could not be parsed. Lines and columns start at 1 and
+/// columns count bytes.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
@@ -0,0 +1,368 @@
+use arrow_array::{Array, RecordBatch};
+use arrow_cast::display::{ArrayFormatter, FormatOptions};
+use arrow_schema::{DataType, SchemaRef};
+use chrono::SecondsFormat;
+#[cfg(feature = "table")]
+use comfy_table::{presets, CellAlignment, Table as TextTable};
+use futures::{Stream, StreamExt as _};
+use serde_json::{Map, Number, Value};
+use tokio::io::{AsyncWrite, AsyncWriteExt as _};
+
+use crate::point_stream::get_arrow_value;
+use crate::{ColumnRole, FieldType, InfluxDBError, PointValue, ResultSchema};
+
+/// How values are written as text. InfluxDB times have no time zone but
+/// are UTC.
+const TEXT_FORMAT: FormatOptions<'static> = FormatOptions::new()
+    .with_null("")
+    .with_timestamp_format(Some("%Y-%m-%dT%H:%M:%S%.fZ"))
+    .with_timestamp_tz_format(Some("%Y-%m-%dT%H:%M:%S%.f%:z"));
+
+/// A file format query results can be exported to with [`export`].
+///
+/// Each call appends the encoded output to `out`, which is written out before
+/// the next call.
+pub trait ExportFormat {
+    /// Called once, before any batch, with the schema of the result.
+    fn start(&mut self, schema: &ResultSchema, out: &mut Vec<u8>) -> Result<(), InfluxDBError>;
+
+    fn write_batch(&mut self, batch: &RecordBatch, out: &mut Vec<u8>) -> Result<(), InfluxDBError>;
+
+    /// Called once, after the last batch.
+    fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), InfluxDBError>;
+}
+
+/// Writes `batches`, whose schema is `schema`, to `writer` in `format`.
+///
+/// Query results are usually exported with
+/// [`RecordBatchStream::export`](crate::RecordBatchStream::export) or
+/// [`PointStream::export`](crate::PointStream::export) instead.
+pub async fn export<S, F, W>(schema: SchemaRef, batches: S, mut format: F, writer: &mut W) -> Result<(), InfluxDBError>
+where
+    S: Stream<Item = Result<RecordBatch, InfluxDBError>>,
+    F: ExportFormat,
+    W: AsyncWrite + Unpin,
+{
+    let mut out = Vec::new();
+    format.start(&ResultSchema::new(schema), &mut out)?;
+    let mut batches = std::pin::pin!(batches);
+    while let Some(batch) = batches.next().await {
+        format.write_batch(&batch?, &mut out)?;
+        writer.write_all(&out).await?;
+        out.clear();
+    }
+    format.finish(&mut out)?;
+    writer.write_all(&out).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Comma-separated values with a header row.
+///
+/// Annotated CSV adds the `#datatype`, `#group` and `#default` rows InfluxDB
+/// uses. The `#datatype` row keeps the role of each column, as `measurement`,
+/// `tag`, `dateTime:RFC3339` or the type of a field, so the file can be
+/// written back as line protocol; tags and the measurement are grouped.
+#[derive(Debug, Default, Clone)]
+pub struct Csv {
+    annotated: bool,
+}
+
+impl Csv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to write InfluxDB annotated CSV. Defaults to `false`.
+    pub fn annotated(mut self, annotated: bool) -> Self {
+        self.annotated = annotated;
+        self
+    }
+
+    fn write_row<I, S>(&self, out: &mut Vec<u8>, cells: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        // Annotated CSV keeps its first column for the annotations.
+        let mut first = !self.annotated;
+        for cell in cells {
+            if !first {
+                out.push(b',');
+            }
+            first = false;
+            let cell = cell.as_ref();
+            if cell.contains([',', '"', '\n', '\r']) {
+                out.push(b'"');
+                out.extend_from_slice(cell.replace('"', "\"\"").as_bytes());
+                out.push(b'"');
+            } else {
+                out.extend_from_slice(cell.as_bytes());
+            }
+        }
+        out.push(b'\n');
+    }
+}
+
+impl ExportFormat for Csv {
+    fn start(&mut self, schema: &ResultSchema, out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        let columns = schema.columns();
+        if self.annotated {
+            let datatypes = columns.iter().map(|column| annotated_datatype(column.role, &column.data_type));
+            out.extend_from_slice(b"#datatype");
+            self.write_row(out, datatypes);
+            let groups = columns.iter()
+                .map(|column| matches!(column.role, ColumnRole::Tag | ColumnRole::Measurement))
+                .map(|group| if group { "true" } else { "false" });
+            out.extend_from_slice(b"#group");
+            self.write_row(out, groups);
+            out.extend_from_slice(b"#default");
+            self.write_row(out, columns.iter().map(|_| ""));
+        }
+        self.write_row(out, columns.iter().map(|column| &column.name));
+        Ok(())
+    }
+
+    fn write_batch(&mut self, batch: &RecordBatch, out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        let formatters = formatters(batch)?;
+        let mut row_cells = vec![String::new(); formatters.len()];
+        for row in 0..batch.num_rows() {
+            for (cell, formatter) in row_cells.iter_mut().zip(&formatters) {
+                cell.clear();
+                formatter.value(row).write(cell)?;
+            }
+            self.write_row(out, &row_cells);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        Ok(())
+    }
+}
+
+/// The annotated CSV type of a column. Columns without a role are typed as
+/// fields.
+fn annotated_datatype(role: ColumnRole, data_type: &DataType) -> &'static str {
+    match role {
+        ColumnRole::Measurement => "measurement",
+        ColumnRole::Tag => "tag",
+        ColumnRole::Timestamp => "dateTime:RFC3339",
+        ColumnRole::Field(FieldType::Integer) => "long",
+        ColumnRole::Field(FieldType::UInteger) => "unsignedLong",
+        ColumnRole::Field(FieldType::Float) => "double",
+        ColumnRole::Field(FieldType::String) => "string",
+        ColumnRole::Field(FieldType::Boolean) => "boolean",
+        ColumnRole::Other => match data_type {
+            DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => "long",
+            DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => "unsignedLong",
+            DataType::Float16 | DataType::Float32 | DataType::Float64 => "double",
+            DataType::Boolean => "boolean",
+            DataType::Timestamp(_, _) => "dateTime:RFC3339",
+            _ => "string",
+        },
+    }
+}
+
+/// One JSON object per row, shaped like a [`Point`](crate::Point):
+/// `{"measurement": .., "tags": {..}, "fields": {..}, "time": ..}`.
+///
+/// Columns without a role go in `fields`, as they do for points. Null tags
+/// and fields are left out; `measurement` and `time` are only written if the
+/// result has such a column.
+#[derive(Debug, Default, Clone)]
+pub struct JsonLines {}
+
+impl JsonLines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ExportFormat for JsonLines {
+    fn start(&mut self, _schema: &ResultSchema, _out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        Ok(())
+    }
+
+    fn write_batch(&mut self, batch: &RecordBatch, out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        let schema = batch.schema();
+        let formatters = formatters(batch)?;
+        for row in 0..batch.num_rows() {
+            let mut object = Map::new();
+            let mut tags = Map::new();
+            let mut fields = Map::new();
+            for ((field, column), formatter) in schema.fields().iter().zip(batch.columns()).zip(&formatters) {
+                let value = match get_arrow_value(column.as_ref(), field.data_type(), field.name(), row)? {
+                    PointValue::Null => Value::Null,
+                    PointValue::Float(v) => Number::from_f64(v).map_or(Value::Null, Value::Number),
+                    PointValue::Integer(v) => v.into(),
+                    PointValue::UInteger(v) => v.into(),
+                    PointValue::Boolean(v) => v.into(),
+                    PointValue::String(v) => v.into(),
+                    PointValue::Timestamp(v) => v.to_rfc3339_opts(SecondsFormat::AutoSi, true).into(),
+                    PointValue::Decimal(..) | PointValue::Interval(..) | PointValue::Binary(_) => {
+                        formatter.value(row).try_to_string()?.into()
+                    },
+                };
+                match ColumnRole::of(field) {
+                    ColumnRole::Measurement => {
+                        object.insert("measurement".to_string(), value);
+                    },
+                    ColumnRole::Timestamp => {
+                        object.insert("time".to_string(), value);
+                    },
+                    _ if value.is_null() => {},
+                    ColumnRole::Tag => {
+                        tags.insert(field.name().clone(), value);
+                    },
+                    ColumnRole::Field(_) | ColumnRole::Other => {
+                        fields.insert(field.name().clone(), value);
+                    },
+                }
+            }
+            object.insert("tags".to_string(), Value::Object(tags));
+            object.insert("fields".to_string(), Value::Object(fields));
+            serde_json::to_writer(&mut *out, &object)?;
+            out.push(b'\n');
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        Ok(())
+    }
+}
+
+/// An aligned plain-text table, with the role of each column under its name
+/// and numbers aligned right.
+///
+/// Column widths depend on every row, so the whole result is held in memory
+/// and written at the end.
+#[cfg(feature = "table")]
+#[derive(Debug)]
+pub struct Table {
+    table: TextTable,
+}
+
+#[cfg(feature = "table")]
+impl Table {
+    pub fn new() -> Self {
+        let mut table = TextTable::new();
+        table.load_preset(presets::ASCII_FULL);
+        Self { table }
+    }
+}
+
+#[cfg(feature = "table")]
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "table")]
+impl ExportFormat for Table {
+    fn start(&mut self, schema: &ResultSchema, _out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        let header: Vec<String> = schema.columns().iter()
+            .map(|column| {
+                let role = match column.role {
+                    ColumnRole::Tag => "tag",
+                    ColumnRole::Field(_) => "field",
+                    ColumnRole::Timestamp => "time",
+                    ColumnRole::Measurement => "measurement",
+                    ColumnRole::Other => "",
+                };
+                format!("{}\n{role}", column.name)
+            })
+            .collect();
+        self.table.set_header(header);
+        for (i, column) in schema.columns().iter().enumerate() {
+            if column.data_type.is_numeric()
+                && let Some(table_column) = self.table.column_mut(i) {
+                table_column.set_cell_alignment(CellAlignment::Right);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_batch(&mut self, batch: &RecordBatch, _out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        let formatters = formatters(batch)?;
+        for row in 0..batch.num_rows() {
+            let cells = formatters.iter()
+                .map(|formatter| formatter.value(row).try_to_string())
+                .collect::<Result<Vec<_>, _>>()?;
+            self.table.add_row(cells);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        out.extend_from_slice(self.table.to_string().as_bytes());
+        out.push(b'\n');
+        Ok(())
+    }
+}
+
+/// Apache Parquet. The Arrow schema is stored in the file, so the column
+/// metadata that gives each column its role is kept.
+#[cfg(feature = "parquet")]
+#[derive(Debug, Default)]
+pub struct Parquet {
+    properties: Option<parquet::file::properties::WriterProperties>,
+    writer: Option<parquet::arrow::ArrowWriter<Vec<u8>>>,
+}
+
+#[cfg(feature = "parquet")]
+impl Parquet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compression, row group size and the other Parquet settings.
+    pub fn properties(mut self, properties: parquet::file::properties::WriterProperties) -> Self {
+        self.properties = Some(properties);
+        self
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl ExportFormat for Parquet {
+    fn start(&mut self, schema: &ResultSchema, _out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        let writer = parquet::arrow::ArrowWriter::try_new(Vec::new(), schema.arrow_schema(), self.properties.take())
+            .map_err(parquet_error)?;
+        self.writer = Some(writer);
+        Ok(())
+    }
+
+    fn write_batch(&mut self, batch: &RecordBatch, out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        writer.write(batch).map_err(parquet_error)?;
+        // The writer counts what it has written, so the bytes of completed
+        // row groups can be handed on.
+        out.append(writer.inner_mut());
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), InfluxDBError> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.finish().map_err(parquet_error)?;
+            out.append(writer.inner_mut());
+        }
+        Ok(())
+    }
+}
+
+/// Parquet errors are kept out of [`InfluxDBError`]'s variants, which would
+/// otherwise depend on the `parquet` feature.
+#[cfg(feature = "parquet")]
+fn parquet_error(err: parquet::errors::ParquetError) -> InfluxDBError {
+    InfluxDBError::ExportError(err.to_string())
+}
+
+fn formatters(batch: &RecordBatch) -> Result<Vec<ArrayFormatter<'_>>, InfluxDBError> {
+    Ok(batch.columns().iter()
+        .map(|column| ArrayFormatter::try_new(column.as_ref() as &dyn Array, &TEXT_FORMAT))
+        .collect::<Result<_, _>>()?)
+}
@@ -0,0 +1,286 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use arrow_array::types::IntervalMonthDayNanoType;
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Decimal128Array, Float64Array, Int64Array, IntervalMonthDayNanoArray, NullArray, RecordBatch,
+    StringArray, TimestampNanosecondArray, UInt64Array,
+};
+use arrow_flight::decode::FlightDataDecoder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_client::FlightServiceClient;
+use arrow_flight::sql::client::{FlightSqlServiceClient, PreparedStatement as RawPreparedStatement};
+use arrow_flight::sql::{CommandGetDbSchemas, CommandGetTables, SqlInfo};
+use arrow_flight::FlightInfo;
+use arrow_schema::SchemaRef;
+use futures::stream::{self, StreamExt as _, TryStreamExt as _};
+use tonic::metadata::AsciiMetadataValue;
+use tonic::transport::Channel;
+
+use crate::record_batch_stream::QueryLimits;
+use crate::{Client, Encode, InfluxDBError, PointValue, QueryOptions, RecordBatchStream};
+
+/// A Flight SQL client for the [`Client`]'s database, sharing its
+/// connection. Created with [`Client::flight_sql`].
+///
+/// Every call returns its results as a [`RecordBatchStream`]; the metadata
+/// calls return the tables the Flight SQL specification describes.
+#[derive(Debug, Clone)]
+pub struct FlightSqlClient {
+    inner: FlightSqlServiceClient<Channel>,
+    /// Fetches results, which the Flight SQL client does not set headers on.
+    flight_client: FlightServiceClient<Channel>,
+    authorization: String,
+    database: String,
+    timeout: Duration,
+    query_timeout: Option<Duration>,
+}
+
+impl FlightSqlClient {
+    pub(crate) fn new(client: &Client) -> Self {
+        let mut inner = FlightSqlServiceClient::new_from_inner(client.flight_client.clone());
+        inner.set_header("authorization", client.authorization.clone());
+        inner.set_header("database", client.database.clone());
+        Self {
+            inner,
+            flight_client: client.flight_client.clone(),
+            authorization: client.authorization.clone(),
+            database: client.database.clone(),
+            timeout: client.timeout,
+            query_timeout: client.query_timeout,
+        }
+    }
+
+    /// Runs a SQL query (`CommandStatementQuery`).
+    pub async fn execute(&mut self, query: &str) -> Result<RecordBatchStream, InfluxDBError> {
+        self.execute_with_options(query, &QueryOptions::default()).await
+    }
+
+    pub async fn execute_with_options(&mut self, query: &str, options: &QueryOptions) -> Result<RecordBatchStream, InfluxDBError> {
+        let mut limits = self.limits(options);
+        let info = limits.start(self.timeout, async { Ok(self.inner.execute(query.to_string(), None).await?) }).await?;
+        self.fetch(info, limits).await
+    }
+
+    /// Plans `query` once so it can be run many times, with different
+    /// parameters. The plan stays on the server until
+    /// [`PreparedStatement::close`] is called.
+    pub async fn prepare(&mut self, query: &str) -> Result<PreparedStatement, InfluxDBError> {
+        let mut limits = self.limits(&QueryOptions::default());
+        let inner = limits.start(self.timeout, async { Ok(self.inner.prepare(query.to_string(), None).await?) }).await?;
+        Ok(PreparedStatement {
+            parameter_schema: Arc::new(inner.parameter_schema()?.clone()),
+            dataset_schema: Arc::new(inner.dataset_schema()?.clone()),
+            inner,
+            client: self.clone(),
+        })
+    }
+
+    /// Lists the catalogs (`GetCatalogs`).
+    pub async fn get_catalogs(&mut self) -> Result<RecordBatchStream, InfluxDBError> {
+        let mut limits = self.limits(&QueryOptions::default());
+        let info = limits.start(self.timeout, async { Ok(self.inner.get_catalogs().await?) }).await?;
+        self.fetch(info, limits).await
+    }
+
+    /// Lists the database schemas matching `request` (`GetDbSchemas`).
+    pub async fn get_db_schemas(&mut self, request: CommandGetDbSchemas) -> Result<RecordBatchStream, InfluxDBError> {
+        let mut limits = self.limits(&QueryOptions::default());
+        let info = limits.start(self.timeout, async { Ok(self.inner.get_db_schemas(request).await?) }).await?;
+        self.fetch(info, limits).await
+    }
+
+    /// Lists the tables matching `request` (`GetTables`).
+    pub async fn get_tables(&mut self, request: CommandGetTables) -> Result<RecordBatchStream, InfluxDBError> {
+        let mut limits = self.limits(&QueryOptions::default());
+        let info = limits.start(self.timeout, async { Ok(self.inner.get_tables(request).await?) }).await?;
+        self.fetch(info, limits).await
+    }
+
+    /// Asks the server about its SQL support (`GetSqlInfo`). An empty `info`
+    /// returns everything the server reports.
+    pub async fn get_sql_info(&mut self, info: Vec<SqlInfo>) -> Result<RecordBatchStream, InfluxDBError> {
+        let mut limits = self.limits(&QueryOptions::default());
+        let info = limits.start(self.timeout, async { Ok(self.inner.get_sql_info(info).await?) }).await?;
+        self.fetch(info, limits).await
+    }
+
+    fn limits(&self, options: &QueryOptions) -> QueryLimits {
+        QueryLimits::new(options, self.query_timeout)
+    }
+
+    /// Reads the results `info` points to, one endpoint after another, from
+    /// the server the client is connected to.
+    async fn fetch(&self, info: FlightInfo, limits: QueryLimits) -> Result<RecordBatchStream, InfluxDBError> {
+        let authorization: AsciiMetadataValue = self.authorization.parse()
+            .map_err(|_| InfluxDBError::InvalidParameter("authorization".to_string(), "<token>".to_string()))?;
+        let database: AsciiMetadataValue = self.database.parse()
+            .map_err(|_| InfluxDBError::InvalidParameter("database".to_string(), self.database.clone()))?;
+        let flight_client = self.flight_client.clone();
+        let tickets = info.endpoint.into_iter().filter_map(|endpoint| endpoint.ticket);
+
+        let data = stream::iter(tickets)
+            .then(move |ticket| {
+                let mut flight_client = flight_client.clone();
+                let mut request = tonic::Request::new(ticket);
+                request.metadata_mut().insert("authorization", authorization.clone());
+                request.metadata_mut().insert("database", database.clone());
+                async move { flight_client.do_get(request).await }
+            })
+            .map_ok(|response| response.into_inner().map_err(FlightError::from))
+            .map_err(FlightError::from)
+            .try_flatten();
+
+        RecordBatchStream::with_limits(FlightDataDecoder::new(data), limits).await
+    }
+}
+
+/// A query planned by the server, which can be run repeatedly with
+/// different parameters. Created with [`FlightSqlClient::prepare`].
+///
+/// Dropping a statement does not release its plan on the server, since that
+/// takes a call; call [`close`](PreparedStatement::close) once done with it.
+#[derive(Debug)]
+pub struct PreparedStatement {
+    inner: RawPreparedStatement<Channel>,
+    client: FlightSqlClient,
+    parameter_schema: SchemaRef,
+    dataset_schema: SchemaRef,
+}
+
+impl PreparedStatement {
+    /// The parameters the query takes, as the server planned them.
+    pub fn parameter_schema(&self) -> SchemaRef {
+        self.parameter_schema.clone()
+    }
+
+    /// The columns the query returns.
+    pub fn dataset_schema(&self) -> SchemaRef {
+        self.dataset_schema.clone()
+    }
+
+    /// Binds `params` for the following executions. Each value is sent with
+    /// the Arrow type of its [`PointValue`] variant, e.g. `Int64` for
+    /// integers and UTC nanosecond timestamps for times.
+    pub fn bind<P, K, V>(&mut self, params: P) -> Result<(), InfluxDBError>
+    where
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Encode,
+    {
+        let columns = params.into_iter()
+            .map(|(name, value)| {
+                let name = name.as_ref().to_string();
+                let array = parameter_array(&name, value.encode())?;
+                Ok((name, array, true))
+            })
+            .collect::<Result<Vec<_>, InfluxDBError>>()?;
+        self.bind_batch(RecordBatch::try_from_iter_with_nullable(columns)?)
+    }
+
+    /// Binds a batch of parameters as is, one column per parameter.
+    pub fn bind_batch(&mut self, params: RecordBatch) -> Result<(), InfluxDBError> {
+        Ok(self.inner.set_parameters(params)?)
+    }
+
+    pub async fn execute(&mut self) -> Result<RecordBatchStream, InfluxDBError> {
+        self.execute_with_options(&QueryOptions::default()).await
+    }
+
+    pub async fn execute_with_options(&mut self, options: &QueryOptions) -> Result<RecordBatchStream, InfluxDBError> {
+        let mut limits = self.client.limits(options);
+        let info = limits.start(self.client.timeout, async { Ok(self.inner.execute().await?) }).await?;
+        self.client.fetch(info, limits).await
+    }
+
+    /// Releases the plan on the server.
+    pub async fn close(self) -> Result<(), InfluxDBError> {
+        let Self { inner, client, .. } = self;
+        let mut limits = client.limits(&QueryOptions::default());
+        limits.start(client.timeout, async { Ok(inner.close().await?) }).await
+    }
+}
+
+/// A single-row column holding `value`.
+fn parameter_array(name: &str, value: PointValue) -> Result<ArrayRef, InfluxDBError> {
+    let array: ArrayRef = match value {
+        PointValue::Null => Arc::new(NullArray::new(1)),
+        PointValue::Float(v) => Arc::new(Float64Array::from(vec![v])),
+        PointValue::Integer(v) => Arc::new(Int64Array::from(vec![v])),
+        PointValue::UInteger(v) => Arc::new(UInt64Array::from(vec![v])),
+        PointValue::Boolean(v) => Arc::new(BooleanArray::from(vec![v])),
+        PointValue::String(v) => Arc::new(StringArray::from(vec![v])),
+        PointValue::Timestamp(v) => {
+            let nanos = v.timestamp_nanos_opt()
+                .ok_or_else(|| InfluxDBError::InvalidParameter(name.to_string(), v.to_rfc3339()))?;
+            Arc::new(TimestampNanosecondArray::from(vec![nanos]).with_timezone("UTC"))
+        },
+        PointValue::Decimal(v, scale) => {
+            Arc::new(Decimal128Array::from(vec![v]).with_precision_and_scale(38, scale)?)
+        },
+        PointValue::Interval(months, days, nanos) => {
+            Arc::new(IntervalMonthDayNanoArray::from(vec![IntervalMonthDayNanoType::make_value(months, days, nanos)]))
+        },
+        PointValue::Binary(v) => Arc::new(BinaryArray::from_vec(vec![&v[..]])),
+    };
+    Ok(array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::Array;
+    use arrow_array::cast::AsArray as _;
+    use arrow_array::types::{Decimal128Type, TimestampNanosecondType};
+    use arrow_schema::{DataType, TimeUnit};
+    use chrono::{DateTime, Utc};
+
+    #[test]
+    fn parameter_types() {
+        let time = DateTime::<Utc>::from_timestamp_nanos(1_700_000_000_123_456_789);
+        let cases = [
+            (PointValue::Null, DataType::Null),
+            (PointValue::Float(1.5), DataType::Float64),
+            (PointValue::Integer(-1), DataType::Int64),
+            (PointValue::UInteger(u64::MAX), DataType::UInt64),
+            (PointValue::Boolean(true), DataType::Boolean),
+            (PointValue::String("a".into()), DataType::Utf8),
+            (PointValue::Timestamp(time), DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))),
+            (PointValue::Decimal(12345, 2), DataType::Decimal128(38, 2)),
+            (PointValue::Interval(1, 2, 3), DataType::Interval(arrow_schema::IntervalUnit::MonthDayNano)),
+            (PointValue::Binary(vec![1, 2]), DataType::Binary),
+        ];
+        for (value, data_type) in cases {
+            let array = parameter_array("p", value.clone()).unwrap();
+            assert_eq!(array.data_type(), &data_type, "{value:?}");
+            assert_eq!(array.len(), 1, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn parameter_values() {
+        let time = DateTime::<Utc>::from_timestamp_nanos(1_700_000_000_123_456_789);
+        let array = parameter_array("p", PointValue::Timestamp(time)).unwrap();
+        assert_eq!(array.as_primitive::<TimestampNanosecondType>().value(0), 1_700_000_000_123_456_789);
+
+        let array = parameter_array("p", PointValue::Decimal(12345, 2)).unwrap();
+        assert_eq!(array.as_primitive::<Decimal128Type>().value_as_string(0), "123.45");
+
+        let array = parameter_array("p", PointValue::String("it's".into())).unwrap();
+        assert_eq!(array.as_string::<i32>().value(0), "it's");
+
+        let array = parameter_array("p", PointValue::Binary(vec![1, 2])).unwrap();
+        assert_eq!(array.as_binary::<i32>().value(0), [1, 2]);
+
+        assert_eq!(parameter_array("p", PointValue::Null).unwrap().logical_null_count(), 1);
+    }
+
+    #[test]
+    fn parameters_arrow_cannot_hold_are_errors() {
+        let time = DateTime::<Utc>::MAX_UTC;
+        let err = parameter_array("p", PointValue::Timestamp(time)).unwrap_err();
+        assert!(matches!(&err, InfluxDBError::InvalidParameter(name, _) if name == "p"), "{err:?}");
+
+        assert!(matches!(parameter_array("p", PointValue::Decimal(1, 39)), Err(InfluxDBError::ArrowError(_))));
+    }
+}
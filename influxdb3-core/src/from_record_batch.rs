@@ -0,0 +1,175 @@
+use arrow_array::cast::AsArray as _;
+use arrow_array::types::{Float64Type, Int64Type, TimestampNanosecondType, UInt64Type};
+use arrow_array::{Array, ArrowPrimitiveType, RecordBatch};
+use arrow_schema::{DataType, TimeUnit};
+use chrono::{DateTime, Utc};
+
+use crate::point_stream::{get_arrow_value, get_point};
+use crate::{ColumnRole, Decode, FromPoint, InfluxDBError};
+
+/// Types built from query results a batch at a time. This is what
+/// [`Client::query_as`](crate::Client::query_as) and the other `*_as`
+/// methods convert rows with.
+///
+/// Usually derived: the derive looks each struct field up by column name once
+/// per batch and decodes the column with [`DecodeColumn`]. A [`FromPoint`]
+/// type without an implementation can be queried through [`ViaPoint`].
+pub trait FromRecordBatch: Sized {
+    /// Converts every row of `batch`. A row that can't be converted gives an
+    /// error in its place and leaves the other rows alone.
+    fn from_record_batch(batch: &RecordBatch) -> Vec<Result<Self, InfluxDBError>>;
+}
+
+/// Converts rows with [`FromPoint`] by building a [`Point`](crate::Point)
+/// for each, so `client.query_as::<ViaPoint<T>>(..)` works for any
+/// `FromPoint` type. Deriving [`FromRecordBatch`] as well is faster.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViaPoint<T>(pub T);
+
+impl<T> ViaPoint<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: FromPoint> FromRecordBatch for ViaPoint<T> {
+    fn from_record_batch(batch: &RecordBatch) -> Vec<Result<Self, InfluxDBError>> {
+        (0..batch.num_rows())
+            .map(|row| get_point(batch, row).and_then(T::from_point).map(ViaPoint))
+            .collect()
+    }
+}
+
+/// Values that can be read from an Arrow column in one pass.
+///
+/// Column types convert the same way they do for [`Point`](crate::Point)
+/// fields, followed by [`Decode`]. The common ones are read straight from
+/// the typed array.
+pub trait DecodeColumn: Sized {
+    /// Decodes every row of `column`, with `None` for nulls. A value that
+    /// can't be converted gives an error for its row only. `name` is only
+    /// used in errors.
+    fn decode_column(column: &dyn Array, name: &str) -> Vec<Result<Option<Self>, InfluxDBError>>;
+}
+
+/// Decodes the column called `name`, or returns `None` if `batch` has no
+/// such column.
+pub fn decode_column<T>(batch: &RecordBatch, name: &str) -> Option<Vec<Result<Option<T>, InfluxDBError>>>
+where
+    T: DecodeColumn,
+{
+    batch.column_by_name(name)
+        .map(|column| T::decode_column(column.as_ref(), name))
+}
+
+/// Decodes the column that [`Point::time`](crate::Point::time) is taken
+/// from, or returns `None` if `batch` has none.
+pub fn decode_time_column(batch: &RecordBatch) -> Option<Vec<Result<Option<DateTime<Utc>>, InfluxDBError>>> {
+    let schema = batch.schema_ref();
+    schema.fields().iter()
+        .position(|field| ColumnRole::of(field) == ColumnRole::Timestamp)
+        .map(|i| DateTime::decode_column(batch.column(i).as_ref(), schema.field(i).name()))
+}
+
+/// Decodes row by row through [`PointValue`](crate::PointValue), for the
+/// column types without a direct path.
+fn decode_rows<T>(column: &dyn Array, name: &str) -> Vec<Result<Option<T>, InfluxDBError>>
+where
+    T: for<'a> Decode<'a>,
+{
+    (0..column.len())
+        .map(|row| get_arrow_value(column, column.data_type(), name, row)?.get_value())
+        .collect()
+}
+
+fn primitive<T, V>(column: &dyn Array, f: impl Fn(T::Native) -> V) -> Vec<Result<Option<V>, InfluxDBError>>
+where
+    T: ArrowPrimitiveType,
+{
+    column.as_primitive::<T>().iter().map(|v| Ok(v.map(&f))).collect()
+}
+
+fn strings<'a>(values: impl Iterator<Item = Option<&'a str>>) -> Vec<Result<Option<String>, InfluxDBError>> {
+    values.map(|v| Ok(v.map(str::to_string))).collect()
+}
+
+impl DecodeColumn for i64 {
+    fn decode_column(column: &dyn Array, name: &str) -> Vec<Result<Option<Self>, InfluxDBError>> {
+        match column.data_type() {
+            DataType::Int64 => primitive::<Int64Type, _>(column, |v| v),
+            _ => decode_rows(column, name),
+        }
+    }
+}
+
+impl DecodeColumn for u64 {
+    fn decode_column(column: &dyn Array, name: &str) -> Vec<Result<Option<Self>, InfluxDBError>> {
+        match column.data_type() {
+            DataType::UInt64 => primitive::<UInt64Type, _>(column, |v| v),
+            _ => decode_rows(column, name),
+        }
+    }
+}
+
+impl DecodeColumn for f64 {
+    fn decode_column(column: &dyn Array, name: &str) -> Vec<Result<Option<Self>, InfluxDBError>> {
+        match column.data_type() {
+            DataType::Float64 => primitive::<Float64Type, _>(column, |v| v),
+            _ => decode_rows(column, name),
+        }
+    }
+}
+
+impl DecodeColumn for bool {
+    fn decode_column(column: &dyn Array, name: &str) -> Vec<Result<Option<Self>, InfluxDBError>> {
+        match column.data_type() {
+            DataType::Boolean => column.as_boolean().iter().map(Ok).collect(),
+            _ => decode_rows(column, name),
+        }
+    }
+}
+
+impl DecodeColumn for String {
+    fn decode_column(column: &dyn Array, name: &str) -> Vec<Result<Option<Self>, InfluxDBError>> {
+        match column.data_type() {
+            DataType::Utf8 => strings(column.as_string::<i32>().iter()),
+            DataType::LargeUtf8 => strings(column.as_string::<i64>().iter()),
+            DataType::Utf8View => strings(column.as_string_view().iter()),
+            _ => decode_rows(column, name),
+        }
+    }
+}
+
+impl DecodeColumn for DateTime<Utc> {
+    fn decode_column(column: &dyn Array, name: &str) -> Vec<Result<Option<Self>, InfluxDBError>> {
+        match column.data_type() {
+            // Every nanosecond timestamp is in range; the other units are not.
+            DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+                primitive::<TimestampNanosecondType, _>(column, DateTime::from_timestamp_nanos)
+            },
+            _ => decode_rows(column, name),
+        }
+    }
+}
+
+/// A null decodes to `Some(None)`, so an `Option` field only needs the
+/// column to exist.
+impl<T: DecodeColumn> DecodeColumn for Option<T> {
+    fn decode_column(column: &dyn Array, name: &str) -> Vec<Result<Option<Self>, InfluxDBError>> {
+        T::decode_column(column, name).into_iter().map(|v| v.map(Some)).collect()
+    }
+}
+
+macro_rules! decode_column_by_row {
+    ($($ty:ty),*) => {
+        $(
+            impl DecodeColumn for $ty {
+                fn decode_column(column: &dyn Array, name: &str) -> Vec<Result<Option<Self>, InfluxDBError>> {
+                    decode_rows(column, name)
+                }
+            }
+        )*
+    };
+}
+
+decode_column_by_row!(i8, i16, i32, u8, u16, u32, f32, Vec<u8>);
@@ -1,6 +1,11 @@
 mod point_stream;
+mod from_record_batch;
+mod record_batch_stream;
+mod result_schema;
 mod point_value;
 mod point;
+mod line_protocol;
+mod line_protocol_reader;
 mod tag_name;
 mod util;
 mod error;
@@ -8,12 +13,39 @@ mod options;
 mod batch_writer;
 mod client;
 mod client_builder;
+mod flight_sql;
+mod schema_discovery;
+mod query_builder;
+mod export;
+mod write_api;
+mod retry;
+mod spool;
 
 pub use crate::point_stream::PointStream;
+pub use crate::record_batch_stream::RecordBatchStream;
+pub use crate::result_schema::{ResultSchema, ColumnInfo, ColumnRole, FieldType};
 pub use crate::point::{Point, ToPoint, FromPoint};
+pub use crate::from_record_batch::{FromRecordBatch, ViaPoint, DecodeColumn, decode_column, decode_time_column};
+pub use crate::line_protocol_reader::LineProtocolReader;
 pub use crate::point_value::{PointValue, Encode, Decode};
 pub use crate::tag_name::{TagMap, TagName};
-pub use crate::error::InfluxDBError;
-pub use crate::options::{TimestampPrecision, QueryType};
+pub use crate::error::{InfluxDBError, PartialWriteError, LineError, ParseError};
+pub use crate::options::{TimestampPrecision, QueryType, QueryOptions, NonFiniteFloatPolicy, WriteOptions};
 pub use crate::client::Client;
-pub use crate::client_builder::ClientBuilder;
\ No newline at end of file
+pub use crate::client_builder::ClientBuilder;
+pub use crate::flight_sql::{FlightSqlClient, PreparedStatement};
+pub use crate::schema_discovery::FieldKey;
+pub use crate::query_builder::{QueryBuilder, Aggregate, Order, quote_identifier, quote_literal};
+pub use crate::write_api::{WriteApi, WriteApiOptions};
+pub use crate::retry::RetryPolicy;
+pub use crate::spool::{DrainReport, SpoolOptions, SpoolOverflow};
+pub use crate::export::{export, ExportFormat, Csv, JsonLines};
+#[cfg(feature = "table")]
+pub use crate::export::Table;
+#[cfg(feature = "parquet")]
+pub use crate::export::Parquet;
+#[cfg(feature = "parquet")]
+pub use parquet::file::properties::WriterProperties;
+pub use arrow_array::RecordBatch;
+pub use tokio_util::sync::CancellationToken;
+pub use arrow_flight::sql::{CommandGetDbSchemas, CommandGetTables, SqlInfo};
\ No newline at end of file
@@ -0,0 +1,467 @@
+use crate::{InfluxDBError, Point, PointValue, TimestampPrecision};
+use crate::error::ParseError;
+use crate::tag_name::TagName;
+
+// Escaping rules from the line protocol reference:
+//   measurement:                      `,` ` ` `\`
+//   tag keys, tag values, field keys: `,` `=` ` ` `\`
+//   string field values:              `"` `\`
+// Newlines cannot be escaped anywhere outside of a string field value, so
+// they are rejected instead of producing a line the server would split.
+
+pub(crate) fn write_measurement(buf: &mut Vec<u8>, name: &str) -> Result<(), InfluxDBError> {
+    if name.is_empty() {
+        return Err(InfluxDBError::InvalidPoint("measurement name is empty".to_string()));
+    }
+    // The server would read the line as blank or as a comment and skip it.
+    if name.as_bytes()[0].is_ascii_whitespace() || name.starts_with('#') {
+        return Err(InfluxDBError::InvalidPoint(format!("measurement name starts with whitespace or '#': {name:?}")));
+    }
+    write_escaped(buf, name, b", \\", "measurement name")
+}
+
+pub(crate) fn write_key(buf: &mut Vec<u8>, key: &str, what: &str) -> Result<(), InfluxDBError> {
+    if key.is_empty() {
+        return Err(InfluxDBError::InvalidPoint(format!("{what} is empty")));
+    }
+    write_escaped(buf, key, b", =\\", what)
+}
+
+pub(crate) fn write_string_field(buf: &mut Vec<u8>, value: &str) {
+    buf.reserve(value.len() + 2);
+    buf.push(b'"');
+    let bytes = value.as_bytes();
+    let mut run = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'"' || b == b'\\' {
+            buf.extend_from_slice(&bytes[run..i]);
+            buf.push(b'\\');
+            run = i;
+        }
+    }
+    buf.extend_from_slice(&bytes[run..]);
+    buf.push(b'"');
+}
+
+pub(crate) fn write_integer<I: itoa::Integer>(buf: &mut Vec<u8>, value: I) {
+    buf.extend_from_slice(itoa::Buffer::new().format(value).as_bytes());
+}
+
+/// Writes the shortest representation that parses back to `value`, which
+/// must be finite. Whole numbers are written without a fractional part.
+pub(crate) fn write_float(buf: &mut Vec<u8>, value: f64) {
+    let mut fmt = ryu::Buffer::new();
+    let s = fmt.format_finite(value);
+    buf.extend_from_slice(s.strip_suffix(".0").unwrap_or(s).as_bytes());
+}
+
+/// Writes `value * 10^-scale` in plain decimal notation.
+pub(crate) fn write_decimal(buf: &mut Vec<u8>, value: i128, scale: i8) {
+    if value < 0 {
+        buf.push(b'-');
+    }
+    let mut digits = itoa::Buffer::new();
+    let digits = digits.format(value.unsigned_abs()).as_bytes();
+    if scale <= 0 {
+        buf.extend_from_slice(digits);
+        if digits != b"0" {
+            buf.resize(buf.len() + (-(scale as i16)) as usize, b'0');
+        }
+        return;
+    }
+    let scale = scale as usize;
+    if digits.len() > scale {
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        buf.extend_from_slice(int);
+        buf.push(b'.');
+        buf.extend_from_slice(frac);
+    } else {
+        buf.extend_from_slice(b"0.");
+        buf.resize(buf.len() + scale - digits.len(), b'0');
+        buf.extend_from_slice(digits);
+    }
+}
+
+/// Copies `value` into `buf`, backslash-escaping the bytes in `special`.
+/// Unescaped runs are copied in one go rather than byte by byte.
+fn write_escaped(buf: &mut Vec<u8>, value: &str, special: &[u8], what: &str) -> Result<(), InfluxDBError> {
+    buf.reserve(value.len());
+    let bytes = value.as_bytes();
+    let mut run = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'\n' || b == b'\r' {
+            return Err(InfluxDBError::InvalidPoint(format!("{what} contains a newline: {value:?}")));
+        }
+        if special.contains(&b) {
+            buf.extend_from_slice(&bytes[run..i]);
+            buf.push(b'\\');
+            run = i;
+        }
+    }
+    buf.extend_from_slice(&bytes[run..]);
+    Ok(())
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    #[default]
+    Start,
+    Comment,
+    Series,
+    Fields,
+    Timestamp,
+}
+
+/// Finds the ends of lines in line protocol that arrives in arbitrary
+/// chunks. A newline inside a string field value does not end the line.
+#[derive(Debug, Default)]
+pub(crate) struct LineScanner {
+    section: Section,
+    escaped: bool,
+    in_string: bool,
+    after_equals: bool,
+}
+
+impl LineScanner {
+    /// Returns the position in `data` of the newline ending the current
+    /// line, if there is one. The scanner picks up after that newline on the
+    /// next call, so the caller passes the remaining bytes next; without a
+    /// newline all of `data` has been consumed.
+    pub fn find_line_end(&mut self, data: &[u8]) -> Option<usize> {
+        for (i, &b) in data.iter().enumerate() {
+            if self.step(b) {
+                *self = Self::default();
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    fn step(&mut self, b: u8) -> bool {
+        if self.escaped {
+            self.escaped = false;
+            return b == b'\n' && !self.in_string;
+        }
+        if self.in_string {
+            match b {
+                b'\\' => self.escaped = true,
+                b'"' => self.in_string = false,
+                _ => {},
+            }
+            return false;
+        }
+        if b == b'\n' {
+            return true;
+        }
+
+        let after_equals = std::mem::take(&mut self.after_equals);
+        match self.section {
+            Section::Start => match b {
+                b'#' => self.section = Section::Comment,
+                b' ' | b'\t' | b'\r' => {},
+                _ => {
+                    self.section = Section::Series;
+                    return self.step(b);
+                },
+            },
+            Section::Comment | Section::Timestamp => {},
+            Section::Series => match b {
+                b'\\' => self.escaped = true,
+                b' ' => self.section = Section::Fields,
+                _ => {},
+            },
+            Section::Fields => match b {
+                b'\\' => self.escaped = true,
+                b'=' => self.after_equals = true,
+                b'"' if after_equals => self.in_string = true,
+                b' ' => self.section = Section::Timestamp,
+                _ => {},
+            },
+        }
+        false
+    }
+}
+
+/// Whether a line carries data, as opposed to being blank or a comment.
+pub(crate) fn is_data_line(line: &[u8]) -> bool {
+    let line = line.trim_ascii_start();
+    !line.is_empty() && line[0] != b'#'
+}
+
+/// Where parsing a line failed: a byte offset into the line and a message.
+pub(crate) type ParseFailure = (usize, String);
+
+/// Parses one line of line protocol, without its trailing newline. A line
+/// without a timestamp gets the current time, as the server would give it.
+pub(crate) fn parse_line(line: &str, precision: TimestampPrecision) -> Result<Point, ParseFailure> {
+    let mut parser = Parser { line: line.as_bytes(), pos: 0 };
+
+    let measurement = parser.read_escaped(b", \n", b", \\");
+    if measurement.is_empty() {
+        return Err((0, "measurement name is empty".to_string()));
+    }
+    let mut point = Point::new_with_measurement(&measurement);
+
+    while parser.eat(b',') {
+        let start = parser.pos;
+        let key = parser.read_escaped(b",= \n", b",= \\");
+        if key.is_empty() {
+            return Err((start, "tag key is empty".to_string()));
+        }
+        parser.expect(b'=', "expected '=' after tag key")?;
+        let start = parser.pos;
+        let value = parser.read_escaped(b", \n", b",= \\");
+        if value.is_empty() {
+            return Err((start, format!("tag {key} has an empty value")));
+        }
+        point.tags.insert(TagName::new_unchecked(key), value);
+    }
+
+    if !parser.skip_spaces() && !parser.at_end() {
+        return Err((parser.pos, "expected a space before the fields".to_string()));
+    }
+    if parser.at_end() {
+        return Err((parser.pos, "line has no fields".to_string()));
+    }
+    loop {
+        let start = parser.pos;
+        let key = parser.read_escaped(b",= \n", b",= \\");
+        if key.is_empty() {
+            return Err((start, "field key is empty".to_string()));
+        }
+        parser.expect(b'=', "expected '=' after field key")?;
+        let value = parser.field_value()?;
+        point.fields.insert(key, value);
+        if !parser.eat(b',') {
+            break;
+        }
+    }
+
+    if parser.skip_spaces() && !parser.at_end() {
+        let start = parser.pos;
+        let digits = parser.take_while(|b| b == b'-' || b.is_ascii_digit());
+        let timestamp = digits.parse::<i64>()
+            .map_err(|_| (start, format!("invalid timestamp {digits:?}")))?;
+        point.time = precision.timestamp_to_datetime(timestamp)
+            .ok_or_else(|| (start, format!("timestamp {timestamp} is out of range")))?;
+        parser.skip_spaces();
+    }
+    if !parser.at_end() {
+        return Err((parser.pos, format!("unexpected character {:?}", parser.line[parser.pos] as char)));
+    }
+    Ok(point)
+}
+
+struct Parser<'a> {
+    line: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn at_end(&self) -> bool {
+        self.pos == self.line.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.line.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        let found = self.peek() == Some(b);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, b: u8, message: &str) -> Result<(), ParseFailure> {
+        if self.eat(b) { Ok(()) } else { Err((self.pos, message.to_string())) }
+    }
+
+    /// Skips spaces and returns whether there were any.
+    fn skip_spaces(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek() == Some(b' ') {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+        // Only ever split at ASCII bytes, so this stays valid UTF-8.
+        std::str::from_utf8(&self.line[start..self.pos]).expect("line is valid utf-8")
+    }
+
+    /// Reads up to the first unescaped byte in `stop`. A backslash escapes
+    /// the bytes in `escapable` and is taken literally before anything else.
+    fn read_escaped(&mut self, stop: &[u8], escapable: &[u8]) -> String {
+        let mut out = Vec::new();
+        while let Some(b) = self.peek() {
+            if b == b'\\' && let Some(&next) = self.line.get(self.pos + 1) && escapable.contains(&next) {
+                out.push(next);
+                self.pos += 2;
+                continue;
+            }
+            if stop.contains(&b) {
+                break;
+            }
+            out.push(b);
+            self.pos += 1;
+        }
+        String::from_utf8(out).expect("line is valid utf-8")
+    }
+
+    fn field_value(&mut self) -> Result<PointValue, ParseFailure> {
+        let start = self.pos;
+        if self.eat(b'"') {
+            let mut out = Vec::new();
+            loop {
+                match self.peek() {
+                    None => return Err((start, "unterminated string field".to_string())),
+                    Some(b'"') => {
+                        self.pos += 1;
+                        break;
+                    },
+                    Some(b'\\') if matches!(self.line.get(self.pos + 1), Some(b'"' | b'\\')) => {
+                        out.push(self.line[self.pos + 1]);
+                        self.pos += 2;
+                    },
+                    Some(b) => {
+                        out.push(b);
+                        self.pos += 1;
+                    },
+                }
+            }
+            return Ok(PointValue::String(String::from_utf8(out).expect("line is valid utf-8")));
+        }
+
+        let raw = self.take_while(|b| !matches!(b, b',' | b' ' | b'\n'));
+        let invalid = || (start, format!("invalid field value {raw:?}"));
+        let value = match raw {
+            "t" | "T" | "true" | "True" | "TRUE" => PointValue::Boolean(true),
+            "f" | "F" | "false" | "False" | "FALSE" => PointValue::Boolean(false),
+            _ if raw.ends_with('i') => PointValue::Integer(raw[..raw.len() - 1].parse().map_err(|_| invalid())?),
+            _ if raw.ends_with('u') => PointValue::UInteger(raw[..raw.len() - 1].parse().map_err(|_| invalid())?),
+            // `f64::from_str` also takes "inf" and "NaN", which line protocol
+            // does not.
+            _ if !raw.is_empty() && raw.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-')) => {
+                let v: f64 = raw.parse().map_err(|_| invalid())?;
+                if !v.is_finite() {
+                    return Err((start, format!("float field value {raw} is out of range")));
+                }
+                PointValue::Float(v)
+            },
+            _ => return Err(invalid()),
+        };
+        Ok(value)
+    }
+}
+
+/// Turns a failure at `offset` into `text`, which starts on line
+/// `first_line`, into an error pointing at the line and column.
+pub(crate) fn parse_error(text: &[u8], first_line: usize, (offset, message): ParseFailure) -> InfluxDBError {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    InfluxDBError::Parse(ParseError {
+        line: first_line + before.iter().filter(|&&b| b == b'\n').count(),
+        column: offset - line_start + 1,
+        message,
+    })
+}
+
+/// Parses a complete logical line, which may span several physical lines
+/// inside string fields, with or without its newline.
+pub(crate) fn parse_text(text: &[u8], first_line: usize, precision: TimestampPrecision) -> Result<Point, InfluxDBError> {
+    let text = text.strip_suffix(b"\n").unwrap_or(text);
+    let text = text.strip_suffix(b"\r").unwrap_or(text);
+    let line = std::str::from_utf8(text)
+        .map_err(|e| parse_error(text, first_line, (e.valid_up_to(), "invalid UTF-8".to_string())))?;
+    parse_line(line, precision).map_err(|failure| parse_error(text, first_line, failure))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &[u8] = b"# a \"comment\n\
+        m,t=a\\\nb v=1i\n\
+        m\"q s=\"a\nb\\\"c\n\\\\\",v=1i 1\r\n\
+        \n\
+        m s=\"x y\" 2\n\
+        m v=1i 3";
+
+    // A newline can't be escaped outside a string, so the backslash before
+    // it does not keep the line going.
+    const LINES: [&[u8]; 7] = [
+        b"# a \"comment",
+        b"m,t=a\\",
+        b"b v=1i",
+        b"m\"q s=\"a\nb\\\"c\n\\\\\",v=1i 1\r",
+        b"",
+        b"m s=\"x y\" 2",
+        b"m v=1i 3",
+    ];
+
+    /// Splits `chunks` into lines the way the batch reader does, carrying
+    /// the unfinished line over to the next chunk.
+    fn split(chunks: &[&[u8]]) -> Vec<Vec<u8>> {
+        let mut scanner = LineScanner::default();
+        let mut lines = vec![];
+        let mut pending = Vec::new();
+        let mut scanned = 0;
+        for chunk in chunks {
+            pending.extend_from_slice(chunk);
+            let mut line_start = 0;
+            while let Some(end) = scanner.find_line_end(&pending[scanned..]) {
+                let end = scanned + end;
+                lines.push(pending[line_start..end].to_vec());
+                line_start = end + 1;
+                scanned = line_start;
+            }
+            pending.drain(..line_start);
+            scanned = pending.len();
+        }
+        if !pending.is_empty() {
+            lines.push(pending);
+        }
+        lines
+    }
+
+    #[test]
+    fn lines_in_one_chunk() {
+        assert_eq!(split(&[INPUT]), LINES);
+    }
+
+    #[test]
+    fn lines_split_into_two_chunks_anywhere() {
+        for i in 0..=INPUT.len() {
+            let (a, b) = INPUT.split_at(i);
+            assert_eq!(split(&[a, b]), LINES, "split at {i}");
+        }
+    }
+
+    #[test]
+    fn lines_split_into_three_chunks_anywhere() {
+        for i in 0..=INPUT.len() {
+            for j in i..=INPUT.len() {
+                assert_eq!(split(&[&INPUT[..i], &INPUT[i..j], &INPUT[j..]]), LINES, "split at {i} and {j}");
+            }
+        }
+    }
+
+    #[test]
+    fn lines_one_byte_at_a_time() {
+        let chunks: Vec<&[u8]> = INPUT.chunks(1).collect();
+        assert_eq!(split(&chunks), LINES);
+    }
+
+    #[test]
+    fn quote_outside_a_field_value_does_not_start_a_string() {
+        assert_eq!(split(&[b"m\"x,t=\"a v=1i \"2\nm v=2i\n"]), [&b"m\"x,t=\"a v=1i \"2"[..], b"m v=2i"]);
+        assert_eq!(split(&[b"m k\\=\"x=1i\nm v=2i\n"]), [&b"m k\\=\"x=1i"[..], b"m v=2i"]);
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::BufRead;
+
+use futures::Stream;
+use futures::stream;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt as _};
+
+use crate::{InfluxDBError, Point, TimestampPrecision};
+use crate::line_protocol::{self, LineScanner};
+
+/// Reads points from line protocol, one line at a time.
+///
+/// Works as an [`Iterator`] over a [`BufRead`], and through
+/// [`next_point`](LineProtocolReader::next_point) or
+/// [`into_stream`](LineProtocolReader::into_stream) over an
+/// [`AsyncBufRead`]. Blank lines and comments are skipped. A line that fails
+/// to parse yields an [`InfluxDBError::Parse`] and reading carries on with
+/// the next line.
+pub struct LineProtocolReader<R> {
+    reader: R,
+    precision: TimestampPrecision,
+    scanner: LineScanner,
+    buf: Vec<u8>,
+    /// Line number the next line starts on.
+    line_number: usize,
+}
+
+impl<R> LineProtocolReader<R> {
+    /// Timestamps are read in `precision`.
+    pub fn new(reader: R, precision: TimestampPrecision) -> Self {
+        Self {
+            reader,
+            precision,
+            scanner: LineScanner::default(),
+            buf: Vec::new(),
+            line_number: 1,
+        }
+    }
+
+    /// Called after each `read_until` with the number of bytes it appended.
+    /// Returns whether `buf` holds a complete line.
+    fn line_complete(&mut self, read: usize) -> bool {
+        if read == 0 {
+            return true;
+        }
+        let appended = self.buf.len() - read;
+        self.scanner.find_line_end(&self.buf[appended..]).is_some()
+    }
+
+    /// Parses the line in `buf`. `None` for blank lines and comments, which
+    /// are skipped.
+    fn take_line(&mut self) -> Option<Result<Point, InfluxDBError>> {
+        let first_line = self.line_number;
+        self.line_number += self.buf.iter().filter(|&&b| b == b'\n').count();
+        self.scanner = LineScanner::default();
+        let line = std::mem::take(&mut self.buf);
+        if !line_protocol::is_data_line(&line) {
+            return None;
+        }
+        Some(line_protocol::parse_text(&line, first_line, self.precision))
+    }
+}
+
+impl<R> Iterator for LineProtocolReader<R>
+where
+    R: BufRead,
+{
+    type Item = Result<Point, InfluxDBError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let read = match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(read) => read,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if read == 0 && self.buf.is_empty() {
+                return None;
+            }
+            if self.line_complete(read) && let Some(result) = self.take_line() {
+                return Some(result);
+            }
+        }
+    }
+}
+
+impl<R> LineProtocolReader<R>
+where
+    R: AsyncBufRead + Unpin,
+{
+    /// Reads the next point, or `None` at the end of the input.
+    pub async fn next_point(&mut self) -> Option<Result<Point, InfluxDBError>> {
+        loop {
+            let read = match self.reader.read_until(b'\n', &mut self.buf).await {
+                Ok(read) => read,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if read == 0 && self.buf.is_empty() {
+                return None;
+            }
+            if self.line_complete(read) && let Some(result) = self.take_line() {
+                return Some(result);
+            }
+        }
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = Result<Point, InfluxDBError>> {
+        stream::unfold(self, |mut reader| async move {
+            reader.next_point().await.map(|point| (point, reader))
+        })
+    }
+}
@@ -1,8 +1,13 @@
-use chrono::DateTime;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 
 use crate::error::InfluxDBError;
+use crate::TagMap;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum QueryType {
     #[default]
@@ -10,7 +15,185 @@ pub enum QueryType {
     InfluxQL
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+impl QueryType {
+    /// The name the server expects in a query ticket.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryType::SQL => "sql",
+            QueryType::InfluxQL => "influxql",
+        }
+    }
+}
+
+/// What to do with `NaN` and infinite float fields, which line protocol
+/// cannot represent.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NonFiniteFloatPolicy {
+    /// Refuse to serialize the point.
+    #[default]
+    Reject,
+    /// Drop the offending field and write the rest of the point.
+    Skip,
+}
+
+/// Per-call overrides for a write. Anything left unset falls back to the
+/// value configured on the [`Client`](crate::Client).
+#[derive(Debug, Default, Clone)]
+pub struct WriteOptions {
+    pub(crate) database: Option<String>,
+    pub(crate) precision: Option<TimestampPrecision>,
+    pub(crate) no_sync: Option<bool>,
+    pub(crate) accept_partial: Option<bool>,
+    pub(crate) default_tags: Option<TagMap>,
+    pub(crate) gzip_threshold: Option<usize>,
+    pub(crate) max_lines: Option<usize>,
+    pub(crate) max_bytes: Option<usize>,
+    pub(crate) max_concurrent_batches: Option<usize>,
+}
+
+impl WriteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn database(mut self, database: &str) -> Self {
+        self.database = Some(database.to_string());
+        self
+    }
+
+    pub fn precision(mut self, precision: TimestampPrecision) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+
+    pub fn no_sync(mut self, no_sync: bool) -> Self {
+        self.no_sync = Some(no_sync);
+        self
+    }
+
+    /// When `false` the server rejects a whole request if any line in it is
+    /// invalid, and the write stops at the first rejected batch.
+    pub fn accept_partial(mut self, accept_partial: bool) -> Self {
+        self.accept_partial = Some(accept_partial);
+        self
+    }
+
+    /// Tags added to every point, in place of the client's default tags.
+    pub fn default_tags(mut self, default_tags: TagMap) -> Self {
+        self.default_tags = Some(default_tags);
+        self
+    }
+
+    pub fn gzip_threshold(mut self, gzip_threshold: usize) -> Self {
+        self.gzip_threshold = Some(gzip_threshold);
+        self
+    }
+
+    /// Maximum number of lines sent in one request.
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = Some(max_lines.max(1));
+        self
+    }
+
+    /// Maximum size of one request body before compression. A single point
+    /// larger than this is refused with
+    /// [`InfluxDBError::PointTooLarge`](crate::InfluxDBError::PointTooLarge).
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes.max(1));
+        self
+    }
+
+    /// Number of batches [`Client::write_stream`](crate::Client::write_stream)
+    /// uploads at the same time.
+    pub fn max_concurrent_batches(mut self, max_concurrent_batches: usize) -> Self {
+        self.max_concurrent_batches = Some(max_concurrent_batches.max(1));
+        self
+    }
+}
+
+/// Per-call limits for a query. Every limit covers the whole query, from
+/// sending it until the last batch is read, and breaking one ends the gRPC
+/// call.
+#[derive(Debug, Default, Clone)]
+pub struct QueryOptions {
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) max_rows: Option<usize>,
+    pub(crate) max_bytes: Option<usize>,
+    pub(crate) cancellation_token: Option<CancellationToken>,
+}
+
+impl QueryOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails the query with [`InfluxDBError::QueryTimeout`] if it is still
+    /// running after `timeout`. Overrides
+    /// [`ClientBuilder::query_timeout`](crate::ClientBuilder::query_timeout).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Like `timeout`, but at a fixed instant. With both set, the earlier
+    /// one applies.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Fails the query with [`InfluxDBError::RowLimitExceeded`] once it
+    /// returns more than `max_rows` rows. The batch that crosses the limit is
+    /// not returned.
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    /// Fails the query with [`InfluxDBError::ByteLimitExceeded`] once the
+    /// batches it returns take more than `max_bytes` bytes of memory.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Fails the query with [`InfluxDBError::QueryCancelled`] when `token` is
+    /// cancelled.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// The instant the query must finish by, given the client default.
+    pub(crate) fn resolve_deadline(&self, default_timeout: Option<Duration>) -> Option<Instant> {
+        let timeout = match (self.timeout, self.deadline) {
+            (None, None) => default_timeout,
+            (timeout, _) => timeout,
+        };
+        let from_timeout = timeout.map(|timeout| Instant::now() + timeout);
+        match (from_timeout, self.deadline) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// [`WriteOptions`] resolved against the client defaults.
+pub(crate) struct WriteConfig<'a> {
+    pub database: &'a str,
+    pub precision: TimestampPrecision,
+    pub no_sync: bool,
+    pub accept_partial: bool,
+    pub default_tags: &'a TagMap,
+    pub gzip_threshold: usize,
+    pub max_lines: usize,
+    pub max_bytes: usize,
+    pub max_concurrent_batches: usize,
+    pub float_policy: NonFiniteFloatPolicy,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
 pub enum TimestampPrecision {
     #[default]
     Nanoseconds,
@@ -54,15 +237,27 @@ impl TryFrom<&str> for TimestampPrecision {
 }
 
 impl TimestampPrecision {
-    pub(crate) fn process_timestamp<Tz>(&self, dt: DateTime<Tz>) -> i64
+    pub(crate) fn process_timestamp<Tz>(&self, dt: DateTime<Tz>) -> Result<i64, InfluxDBError>
     where
         Tz: chrono::TimeZone,
     {
         match self {
-            TimestampPrecision::Nanoseconds => dt.timestamp_nanos_opt().expect("Timestamp out of range"),
-            TimestampPrecision::Microseconds => dt.timestamp_micros(),
-            TimestampPrecision::Milliseconds => dt.timestamp_millis(),
-            TimestampPrecision::Seconds => dt.timestamp(),
+            TimestampPrecision::Nanoseconds => dt.timestamp_nanos_opt()
+                .ok_or_else(|| InfluxDBError::InvalidPoint(format!("timestamp out of range for nanosecond precision: {}", dt.to_utc()))),
+            TimestampPrecision::Microseconds => Ok(dt.timestamp_micros()),
+            TimestampPrecision::Milliseconds => Ok(dt.timestamp_millis()),
+            TimestampPrecision::Seconds => Ok(dt.timestamp()),
+        }
+    }
+
+    /// The inverse of `process_timestamp`. `None` if `timestamp` is out of
+    /// range.
+    pub(crate) fn timestamp_to_datetime(&self, timestamp: i64) -> Option<DateTime<Utc>> {
+        match self {
+            TimestampPrecision::Nanoseconds => Some(DateTime::from_timestamp_nanos(timestamp)),
+            TimestampPrecision::Microseconds => DateTime::from_timestamp_micros(timestamp),
+            TimestampPrecision::Milliseconds => DateTime::from_timestamp_millis(timestamp),
+            TimestampPrecision::Seconds => DateTime::from_timestamp(timestamp, 0),
         }
     }
 }
\ No newline at end of file
@@ -2,12 +2,15 @@ use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 
 use crate::InfluxDBError;
-use crate::options::TimestampPrecision;
+use crate::line_protocol;
+use crate::options::{NonFiniteFloatPolicy, TimestampPrecision};
 use crate::{Decode, Encode, PointValue};
 use crate::util::validate_name;
 use crate::tag_name::{TagMap, TagName};
 
-#[derive(Debug, Default, Clone)]
+/// A single point. Tags and fields are serialized sorted by key, so a point
+/// always serializes to the same line.
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Point {
     pub measurement_name: String,
     pub tags: TagMap,
@@ -19,7 +22,7 @@ impl Point {
     pub fn new_with_measurement(measurement_name: &str) -> Self {
         Self {
             measurement_name: measurement_name.to_string(),
-            tags: HashMap::new(),
+            tags: TagMap::new(),
             fields: HashMap::new(),
             time: Utc::now(),
         }
@@ -128,38 +131,102 @@ impl Point {
         self.fields.is_empty()
     }
 
-    pub(crate) fn serialize(&self, buf: &mut Vec<u8>, precision: TimestampPrecision, default_tags: &TagMap) {
-        // <measurement>[,<tag_key>=<tag_value>[,<tag_key>=<tag_value>]] <field_key>=<field_value>[,<field_key>=<field_value>] [<timestamp>]
-        buf.extend(self.measurement_name.as_bytes());
-        for (tag_key, tag_value) in default_tags {
-            buf.push(b',');
-            buf.extend(tag_key.as_bytes());
-            buf.push(b'=');
-            buf.extend(tag_value.as_bytes());
+    /// Parses a single line of line protocol, the inverse of
+    /// [`to_line_protocol`](Point::to_line_protocol). A trailing newline is
+    /// allowed. Timestamps are read in `precision`, and a line without one
+    /// gets the current time.
+    ///
+    /// Use [`LineProtocolReader`](crate::LineProtocolReader) for input with
+    /// more than one line.
+    pub fn from_line_protocol(line: &str, precision: TimestampPrecision) -> Result<Point, InfluxDBError> {
+        let bytes = line.as_bytes();
+        let mut scanner = line_protocol::LineScanner::default();
+        let line = match scanner.find_line_end(bytes) {
+            Some(end) if !bytes[end + 1..].trim_ascii().is_empty() => {
+                return Err(line_protocol::parse_error(bytes, 1, (end + 1, "expected a single line".to_string())));
+            },
+            Some(end) => &bytes[..end],
+            None => bytes,
+        };
+        line_protocol::parse_text(line, 1, precision)
+    }
+
+    /// Appends the point to `buf` as one line of line protocol.
+    ///
+    /// Tags are written sorted by key, with the point's own tags taking
+    /// precedence over `default_tags` of the same name. Null fields are left
+    /// out, and a point without any remaining fields is refused since the
+    /// server would reject the line. Tags with an empty value are dropped,
+    /// so an empty point tag removes the default tag of the same name. On
+    /// error `buf` is left as it was.
+    pub fn serialize(&self, buf: &mut Vec<u8>, precision: TimestampPrecision, default_tags: &TagMap, float_policy: NonFiniteFloatPolicy) -> Result<(), InfluxDBError> {
+        let start = buf.len();
+        let result = self.serialize_inner(buf, precision, default_tags, float_policy);
+        if result.is_err() {
+            buf.truncate(start);
         }
-        for (tag_key, tag_value) in &self.tags {
+        result
+    }
+
+    /// Renders the point as a single line of line protocol, without the
+    /// trailing newline.
+    pub fn to_line_protocol(&self, precision: TimestampPrecision) -> Result<String, InfluxDBError> {
+        let mut buf = Vec::new();
+        self.serialize(&mut buf, precision, &TagMap::new(), NonFiniteFloatPolicy::Reject)?;
+        buf.pop();
+        Ok(String::from_utf8(buf).expect("line protocol is valid utf-8"))
+    }
+
+    fn serialize_inner(&self, buf: &mut Vec<u8>, precision: TimestampPrecision, default_tags: &TagMap, float_policy: NonFiniteFloatPolicy) -> Result<(), InfluxDBError> {
+        // <measurement>[,<tag_key>=<tag_value>[,<tag_key>=<tag_value>]] <field_key>=<field_value>[,<field_key>=<field_value>] [<timestamp>]
+        line_protocol::write_measurement(buf, &self.measurement_name)?;
+        for (tag_key, tag_value) in merge_tags(default_tags, &self.tags) {
+            if tag_value.is_empty() {
+                continue;
+            }
             buf.push(b',');
-            buf.extend(tag_key.as_ref().as_bytes());
+            line_protocol::write_key(buf, tag_key, "tag key")?;
             buf.push(b'=');
-            buf.extend(tag_value.as_bytes());
+            line_protocol::write_key(buf, tag_value, "tag value")?;
         }
         buf.push(b' ');
         let mut first_field = true;
-        for (field_key, field_value) in &self.fields {
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_unstable_by_key(|(key, _)| *key);
+        for (field_key, field_value) in fields {
+            let field_start = buf.len();
             if !first_field {
                 buf.push(b',');
             }
-            first_field = false;
-            buf.extend(field_key.as_bytes());
+            line_protocol::write_key(buf, field_key, "field key")?;
             buf.push(b'=');
-            buf.extend(field_value.serialize().as_bytes());
+            if field_value.serialize(buf, field_key, precision, float_policy)? {
+                first_field = false;
+            } else {
+                buf.truncate(field_start);
+            }
+        }
+        if first_field {
+            return Err(InfluxDBError::InvalidPoint(format!("point in measurement {} has no fields", self.measurement_name)));
         }
         buf.push(b' ');
-        buf.extend(precision.process_timestamp(self.time).to_string().as_bytes());
+        line_protocol::write_integer(buf, precision.process_timestamp(self.time)?);
         buf.push(b'\n');
+        Ok(())
     }
 }
 
+/// The union of two tag maps sorted by key. Keys present in both come from
+/// `tags`.
+fn merge_tags<'a>(default_tags: &'a TagMap, tags: &'a TagMap) -> Vec<(&'a TagName, &'a String)> {
+    let mut merged: Vec<_> = default_tags.iter()
+        .filter(|(key, _)| !tags.contains_key(*key))
+        .chain(tags)
+        .collect();
+    merged.sort_unstable_by_key(|(key, _)| *key);
+    merged
+}
+
 pub trait FromPoint {
     fn from_point(point: Point) -> Result<Self, InfluxDBError>
     where
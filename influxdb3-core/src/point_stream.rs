@@ -1,42 +1,51 @@
 use arrow_array::cast::{as_boolean_array, as_primitive_array, as_string_array};
-use arrow_array::{Array, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray, TimestampMillisecondArray, TimestampNanosecondArray, TimestampSecondArray};
-use arrow_flight::decode::FlightRecordBatchStream;
-use arrow_schema::{DataType, Field, TimeUnit};
+use arrow_array::cast::AsArray as _;
+use arrow_array::types::{Date32Type, Date64Type, Decimal128Type, Float32Type, Int16Type, Int32Type, Int8Type, IntervalDayTimeType, IntervalMonthDayNanoType, IntervalYearMonthType, TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type, UInt64Type, UInt8Type};
+use arrow_array::{downcast_dictionary_array, Array, ArrowPrimitiveType, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, IntervalUnit, TimeUnit};
 use chrono::DateTime;
-use futures::{Stream, StreamExt as _};
+use futures::stream::{self, Stream, StreamExt as _};
+use tokio::io::AsyncWrite;
 
-use crate::Point;
+use crate::{export, ColumnRole, ExportFormat, Point, RecordBatchStream, ResultSchema};
+use crate::tag_name::TagName;
 use crate::InfluxDBError;
 use crate::PointValue;
 
-enum ColumnType {
-    Integer,
-    UInteger,
-    Float,
-    String,
-    Boolean,
-    Tag,
-    Timestamp,
-    Unknown,
+/// Row of the dictionary values that row `row` of `array` refers to.
+fn dictionary_key(array: &dyn Array, row: usize) -> Option<usize> {
+    downcast_dictionary_array! {
+        array => array.key(row),
+        _ => None,
+    }
 }
 
-impl From<&str> for ColumnType {
-    fn from(s: &str) -> Self {
-        match s {
-            "iox::column_type::field::integer" => ColumnType::Integer,
-            "iox::column_type::field::uinteger" => ColumnType::UInteger,
-            "iox::column_type::field::float" => ColumnType::Float,
-            "iox::column_type::field::string" => ColumnType::String,
-            "iox::column_type::field::boolean" => ColumnType::Boolean,
-            "iox::column_type::tag" => ColumnType::Tag,
-            "iox::column_type::timestamp" => ColumnType::Timestamp,
-            _ => ColumnType::Unknown,
-        }
-    }
+/// `Null` for a null slot, `f(row)` otherwise.
+fn nullable(array: &dyn Array, row: usize, f: impl FnOnce(usize) -> PointValue) -> PointValue {
+    if array.is_null(row) { PointValue::Null } else { f(row) }
+}
+
+fn primitive<T>(array: &dyn Array, row: usize, f: impl FnOnce(T::Native) -> PointValue) -> PointValue
+where
+    T: ArrowPrimitiveType,
+{
+    nullable(array, row, |row| f(array.as_primitive::<T>().value(row)))
 }
 
-fn get_arrow_value(array: &dyn Array, field: &Field, row: usize) -> Result<PointValue, InfluxDBError> {
-    match field.data_type() {
+/// Turns a date held as `Integer` units of `unit_millis` into a timestamp
+/// at midnight UTC.
+fn date(value: PointValue, unit_millis: i64, name: &str, data_type: &DataType) -> Result<PointValue, InfluxDBError> {
+    let PointValue::Integer(units) = value else {
+        return Ok(value);
+    };
+    units.checked_mul(unit_millis)
+        .and_then(DateTime::from_timestamp_millis)
+        .map(PointValue::Timestamp)
+        .ok_or_else(|| InfluxDBError::InvalidPointValue(name.to_string(), format!("{data_type} out of range: {units}")))
+}
+
+pub(crate) fn get_arrow_value(array: &dyn Array, data_type: &DataType, name: &str, row: usize) -> Result<PointValue, InfluxDBError> {
+    match data_type {
         DataType::Null => Ok(PointValue::Null),
         DataType::Boolean => {
             let arr: &BooleanArray = as_boolean_array(array);
@@ -62,14 +71,7 @@ fn get_arrow_value(array: &dyn Array, field: &Field, row: usize) -> Result<Point
                 Ok(PointValue::Integer(arr.value(row)))
             }
         },
-        DataType::UInt64 => {
-            let arr: &Int64Array = as_primitive_array(array);
-            if arr.is_null(row) {
-                Ok(PointValue::Null)
-            } else {
-                Ok(PointValue::UInteger(arr.value(row) as u64))
-            }
-        },
+        DataType::UInt64 => Ok(primitive::<UInt64Type>(array, row, PointValue::UInteger)),
         DataType::Utf8 => {
             let arr: &StringArray = as_string_array(array);
             if arr.is_null(row) {
@@ -78,89 +80,103 @@ fn get_arrow_value(array: &dyn Array, field: &Field, row: usize) -> Result<Point
                 Ok(PointValue::String(arr.value(row).to_string()))
             }
         },
-        DataType::Timestamp(unit, _tz ) => {
-            match unit {
+        // A timestamp counts from the Unix epoch in UTC whether or not the
+        // column has a time zone, which only affects how it is displayed.
+        DataType::Timestamp(unit, _) => {
+            if array.is_null(row) {
+                return Ok(PointValue::Null);
+            }
+            let (value, time) = match unit {
                 TimeUnit::Second => {
-                    let arr: &TimestampSecondArray = as_primitive_array(array);
-                    if arr.is_null(row) {
-                        Ok(PointValue::Null)
-                    } else {
-                        Ok(PointValue::Timestamp(DateTime::from_timestamp_secs(arr.value(row)).expect("Invalid timestamp")))
-                    }
+                    let v = array.as_primitive::<TimestampSecondType>().value(row);
+                    (v, DateTime::from_timestamp(v, 0))
                 },
                 TimeUnit::Millisecond => {
-                    let arr: &TimestampMillisecondArray = as_primitive_array(array);
-                    if arr.is_null(row) {
-                        Ok(PointValue::Null)
-                    } else {
-                        Ok(PointValue::Timestamp(DateTime::from_timestamp_millis(arr.value(row)).expect("Invalid timestamp")))
-                    }
+                    let v = array.as_primitive::<TimestampMillisecondType>().value(row);
+                    (v, DateTime::from_timestamp_millis(v))
                 },
                 TimeUnit::Microsecond => {
-                    let arr: &TimestampMicrosecondArray = as_primitive_array(array);
-                    if arr.is_null(row) {
-                        Ok(PointValue::Null)
-                    } else {
-                        Ok(PointValue::Timestamp(DateTime::from_timestamp_micros(arr.value(row)).expect("Invalid timestamp")))
-                    }
+                    let v = array.as_primitive::<TimestampMicrosecondType>().value(row);
+                    (v, DateTime::from_timestamp_micros(v))
                 },
                 TimeUnit::Nanosecond => {
-                    let arr: &TimestampNanosecondArray = as_primitive_array(array);
-                    if arr.is_null(row) {
-                        Ok(PointValue::Null)
-                    } else {
-                        Ok(PointValue::Timestamp(DateTime::from_timestamp_nanos(arr.value(row))))
-                    }
+                    let v = array.as_primitive::<TimestampNanosecondType>().value(row);
+                    (v, Some(DateTime::from_timestamp_nanos(v)))
                 },
-            }
+            };
+            time.map(PointValue::Timestamp)
+                .ok_or_else(|| InfluxDBError::InvalidPointValue(name.to_string(), format!("{data_type} out of range: {value}")))
+        },
+        DataType::LargeUtf8 => Ok(nullable(array, row, |row| PointValue::String(array.as_string::<i64>().value(row).to_string()))),
+        DataType::Utf8View => Ok(nullable(array, row, |row| PointValue::String(array.as_string_view().value(row).to_string()))),
+        DataType::Int8 => Ok(primitive::<Int8Type>(array, row, |v| PointValue::Integer(v.into()))),
+        DataType::Int16 => Ok(primitive::<Int16Type>(array, row, |v| PointValue::Integer(v.into()))),
+        DataType::Int32 => Ok(primitive::<Int32Type>(array, row, |v| PointValue::Integer(v.into()))),
+        DataType::UInt8 => Ok(primitive::<UInt8Type>(array, row, |v| PointValue::UInteger(v.into()))),
+        DataType::UInt16 => Ok(primitive::<UInt16Type>(array, row, |v| PointValue::UInteger(v.into()))),
+        DataType::UInt32 => Ok(primitive::<UInt32Type>(array, row, |v| PointValue::UInteger(v.into()))),
+        DataType::Float32 => Ok(primitive::<Float32Type>(array, row, |v| PointValue::Float(v.into()))),
+        DataType::Date32 => {
+            let days = primitive::<Date32Type>(array, row, |v| PointValue::Integer(v.into()));
+            date(days, 86_400_000, name, data_type)
+        },
+        DataType::Date64 => {
+            let millis = primitive::<Date64Type>(array, row, PointValue::Integer);
+            date(millis, 1, name, data_type)
         },
-        _ => Err(InfluxDBError::InvalidPointValue(field.name().to_string(), field.data_type().to_string())),
+        DataType::Decimal128(_, scale) => Ok(primitive::<Decimal128Type>(array, row, |v| PointValue::Decimal(v, *scale))),
+        DataType::Interval(IntervalUnit::YearMonth) => {
+            Ok(primitive::<IntervalYearMonthType>(array, row, |v| PointValue::Interval(v, 0, 0)))
+        },
+        DataType::Interval(IntervalUnit::DayTime) => {
+            Ok(primitive::<IntervalDayTimeType>(array, row, |v| PointValue::Interval(0, v.days, v.milliseconds as i64 * 1_000_000)))
+        },
+        DataType::Interval(IntervalUnit::MonthDayNano) => {
+            Ok(primitive::<IntervalMonthDayNanoType>(array, row, |v| PointValue::Interval(v.months, v.days, v.nanoseconds)))
+        },
+        DataType::Binary => Ok(nullable(array, row, |row| PointValue::Binary(array.as_binary::<i32>().value(row).to_vec()))),
+        DataType::LargeBinary => Ok(nullable(array, row, |row| PointValue::Binary(array.as_binary::<i64>().value(row).to_vec()))),
+        DataType::BinaryView => Ok(nullable(array, row, |row| PointValue::Binary(array.as_binary_view().value(row).to_vec()))),
+        DataType::FixedSizeBinary(_) => Ok(nullable(array, row, |row| PointValue::Binary(array.as_fixed_size_binary().value(row).to_vec()))),
+        // InfluxQL results dictionary-encode the measurement and tag columns.
+        DataType::Dictionary(_, value_type) => match dictionary_key(array, row) {
+            Some(key) => get_arrow_value(array.as_any_dictionary().values().as_ref(), value_type, name, key),
+            None => Ok(PointValue::Null),
+        },
+        _ => Err(InfluxDBError::InvalidPointValue(name.to_string(), data_type.to_string())),
     }
 }
 
-fn get_point(batch: &RecordBatch, row: usize) -> Result<Point, InfluxDBError> {
+pub(crate) fn get_point(batch: &RecordBatch, row: usize) -> Result<Point, InfluxDBError> {
     let mut point = Point::default();
 
     for (i, field) in batch.schema().fields().iter().enumerate() {
         let array = batch.column(i);
-        let column_type = ColumnType::from(
-            field.metadata()
-                .get("iox::column::type")
-                .map(String::as_str)
-                .unwrap_or(""),
-        );
-        let value = get_arrow_value(array.as_ref(), field, row)?;
         let name = field.name();
-        
-        if matches!(field.data_type(), DataType::Utf8) && (name == "measurement" || name == "iox::measurement") {
-            if let Some(v) = value.get_value()? {
-                point.set_measurement(v);
-                continue;
-            }
+        let value = get_arrow_value(array.as_ref(), field.data_type(), name, row)?;
+
+        if (name == "measurement" || name == "iox::measurement")
+            && let PointValue::String(v) = &value {
+            point.set_measurement(v);
+            continue;
         }
 
-        match column_type {
-            ColumnType::Unknown => {
-                if matches!(field.data_type(), DataType::Timestamp(_, _)) && name == "time" {
-                    if let Some(v) = value.get_value()? {
-                        point.set_timestamp(v);
-                    }
-                } else {
-                    point.set_field(name, value);
-                }
-            },
-            ColumnType::Tag => {
+        match ColumnRole::of(field) {
+            ColumnRole::Timestamp => {
                 if let Some(v) = value.get_value()? {
-                    point.set_tag(name, v);
+                    point.set_timestamp(v);
                 }
             },
-            ColumnType::Timestamp => {
-                if let Some(v) = value.get_value()? {
-                    point.set_timestamp(v);
+            // Column names come from the server and need not pass the checks
+            // `set_tag` and `set_field` apply to names chosen by callers,
+            // e.g. `count(*)`.
+            ColumnRole::Tag => {
+                if let PointValue::String(v) = value {
+                    point.tags.insert(TagName::new_unchecked(name.clone()), v);
                 }
             },
             _ => {
-                point.set_field(name, value);
+                point.fields.insert(name.clone(), value);
             }
         }
     }
@@ -168,20 +184,41 @@ fn get_point(batch: &RecordBatch, row: usize) -> Result<Point, InfluxDBError> {
     Ok(point)
 }
 
+/// Query results converted to [`Point`]s row by row. A thin adapter over
+/// [`RecordBatchStream`].
 #[derive(Debug)]
 pub struct PointStream {
-    inner: FlightRecordBatchStream,
+    inner: RecordBatchStream,
     batch_buffer: Option<RecordBatch>,
     i: usize,
     len: usize,
 }
 
 impl PointStream {
-    pub fn new(inner: FlightRecordBatchStream) -> Self {
+    pub fn new(inner: RecordBatchStream) -> Self {
         Self { inner, batch_buffer: None, i: 0, len: 0 }
     }
-}
 
+    /// The columns the points are built from.
+    pub fn result_schema(&self) -> ResultSchema {
+        self.inner.result_schema()
+    }
+
+    /// Writes the points not read yet to `writer` in `format`, as the
+    /// columns they are built from.
+    pub async fn export<F, W>(self, format: F, writer: &mut W) -> Result<(), InfluxDBError>
+    where
+        F: ExportFormat,
+        W: AsyncWrite + Unpin,
+    {
+        let schema = self.inner.schema();
+        let unread = self.batch_buffer
+            .map(|batch| batch.slice(self.i, self.len - self.i))
+            .filter(|batch| batch.num_rows() > 0);
+        let batches = stream::iter(unread.map(Ok)).chain(self.inner);
+        export(schema, batches, format, writer).await
+    }
+}
 impl Stream for PointStream {
     type Item = Result<Point, InfluxDBError>;
 
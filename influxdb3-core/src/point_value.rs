@@ -1,8 +1,10 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 
 use crate::error::InfluxDBError;
+use crate::line_protocol;
+use crate::options::{NonFiniteFloatPolicy, TimestampPrecision};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PointValue {
     Null,
     Float(f64),
@@ -10,20 +12,72 @@ pub enum PointValue {
     UInteger(u64),
     Boolean(bool),
     String(String),
-    Timestamp(DateTime<Utc>)
+    Timestamp(DateTime<Utc>),
+    /// An unscaled value and a scale: `Decimal(12345, 2)` is 123.45.
+    Decimal(i128, i8),
+    /// Months, days and nanoseconds, kept apart because their lengths vary.
+    Interval(i32, i32, i64),
+    Binary(Vec<u8>),
 }
 
 impl PointValue {
-    pub(crate) fn serialize(&self) -> String {
+    /// Appends the value to `buf` as a line protocol field value. Returns
+    /// `false`, having written nothing, if the field should be left out of
+    /// the line entirely.
+    pub(crate) fn serialize(&self, buf: &mut Vec<u8>, key: &str, precision: TimestampPrecision, float_policy: NonFiniteFloatPolicy) -> Result<bool, InfluxDBError> {
         match self {
-            PointValue::Null => String::new(),
-            PointValue::Float(v) => v.to_string(),
-            PointValue::Integer(v) => format!("{v}i"),
-            PointValue::UInteger(v) => format!("{}u", v),
-            PointValue::Boolean(v) => if *v { "t".to_string() } else { "f".to_string() },
-            PointValue::String(v) => format!("\"{}\"", v.replace("\\", "\\\\".into()).replace("\"", "\\\"".into())),
-            PointValue::Timestamp(v) => v.timestamp_nanos_opt().expect("Invalid timestamp".into()).to_string(),
+            PointValue::Null => return Ok(false),
+            PointValue::Float(v) if !v.is_finite() => match float_policy {
+                NonFiniteFloatPolicy::Reject => return Err(InfluxDBError::InvalidPoint(format!("field {key} is not a finite float: {v}"))),
+                NonFiniteFloatPolicy::Skip => return Ok(false),
+            },
+            PointValue::Float(v) => line_protocol::write_float(buf, *v),
+            PointValue::Integer(v) => {
+                line_protocol::write_integer(buf, *v);
+                buf.push(b'i');
+            },
+            PointValue::UInteger(v) => {
+                line_protocol::write_integer(buf, *v);
+                buf.push(b'u');
+            },
+            PointValue::Boolean(v) => buf.push(if *v { b't' } else { b'f' }),
+            PointValue::String(v) => line_protocol::write_string_field(buf, v),
+            // Line protocol has no timestamp field type, so timestamps are
+            // written as integers in the precision of the write.
+            PointValue::Timestamp(v) => {
+                line_protocol::write_integer(buf, precision.process_timestamp(*v)?);
+                buf.push(b'i');
+            },
+            // Written as a float, digit for digit.
+            PointValue::Decimal(v, scale) => line_protocol::write_decimal(buf, *v, *scale),
+            PointValue::Interval(..) | PointValue::Binary(_) => {
+                return Err(InfluxDBError::InvalidPoint(format!("field {key} has a type line protocol cannot represent: {self:?}")));
+            },
         }
+        Ok(true)
+    }
+
+    /// The value as JSON, for query parameters. Timestamps become RFC 3339
+    /// strings and decimals strings with every digit, since a JSON number
+    /// would go through `f64`. `None` for non-finite floats, intervals and
+    /// binary values.
+    pub(crate) fn to_json(&self) -> Option<serde_json::Value> {
+        let value = match self {
+            PointValue::Null => serde_json::Value::Null,
+            PointValue::Float(v) => serde_json::Number::from_f64(*v)?.into(),
+            PointValue::Integer(v) => (*v).into(),
+            PointValue::UInteger(v) => (*v).into(),
+            PointValue::Boolean(v) => (*v).into(),
+            PointValue::String(v) => v.as_str().into(),
+            PointValue::Timestamp(v) => v.to_rfc3339_opts(SecondsFormat::AutoSi, true).into(),
+            PointValue::Decimal(v, scale) => {
+                let mut buf = Vec::new();
+                line_protocol::write_decimal(&mut buf, *v, *scale);
+                String::from_utf8(buf).ok()?.into()
+            },
+            PointValue::Interval(..) | PointValue::Binary(_) => return None,
+        };
+        Some(value)
     }
 
     pub fn get_value<'a, T>(&'a self) -> Result<Option<T>, InfluxDBError>
@@ -157,6 +211,18 @@ impl Encode for String {
     }
 }
 
+impl Encode for Vec<u8> {
+    fn encode(self) -> PointValue {
+        PointValue::Binary(self)
+    }
+}
+
+impl Encode for &[u8] {
+    fn encode(self) -> PointValue {
+        PointValue::Binary(self.to_vec())
+    }
+}
+
 impl Encode for DateTime<Utc> {
     fn encode(self) -> PointValue {
         PointValue::Timestamp(self)
@@ -300,4 +366,22 @@ impl<'a> Decode<'a> for DateTime<Utc> {
             _ => Err(InfluxDBError::InvalidPointValueConversion("PointValue is not a Timestamp".into())),
         }
     }
-}
\ No newline at end of file
+}
+
+impl<'a> Decode<'a> for Vec<u8> {
+    fn decode(value: &'a PointValue) -> Result<Self, InfluxDBError> {
+        match value {
+            PointValue::Binary(v) => Ok(v.clone()),
+            _ => Err(InfluxDBError::InvalidPointValueConversion("PointValue is not Binary".into())),
+        }
+    }
+}
+
+impl<'a> Decode<'a> for &'a [u8] {
+    fn decode(value: &'a PointValue) -> Result<Self, InfluxDBError> {
+        match value {
+            PointValue::Binary(v) => Ok(v.as_slice()),
+            _ => Err(InfluxDBError::InvalidPointValueConversion("PointValue is not Binary".into())),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt::Write as _;
+use std::ops::{Bound, RangeBounds};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::PointValue;
+
+/// `name` as a double-quoted SQL identifier, so it is taken literally
+/// whatever its case or characters.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// `value` as a single-quoted SQL string literal. Prefer query parameters
+/// where the server accepts them.
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// An aggregate function for [`QueryBuilder::aggregate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::Sum => "sum",
+            Aggregate::Avg => "avg",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Builds a SQL query over one table for
+/// [`Client::query_with_params`](crate::Client::query_with_params).
+///
+/// Table and column names are quoted and values are passed as parameters,
+/// so neither needs escaping by the caller.
+#[derive(Debug, Clone)]
+pub struct QueryBuilder {
+    table: String,
+    columns: Vec<String>,
+    filters: Vec<String>,
+    group_by: Vec<String>,
+    order_by: Vec<String>,
+    limit: Option<usize>,
+    params: Vec<(String, PointValue)>,
+}
+
+impl QueryBuilder {
+    pub fn new(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            columns: Vec::new(),
+            filters: Vec::new(),
+            group_by: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            params: Vec::new(),
+        }
+    }
+
+    /// Adds a column to the result. Without any, the query selects `*`.
+    pub fn select(mut self, column: &str) -> Self {
+        self.columns.push(quote_identifier(column));
+        self
+    }
+
+    /// Adds `function(column) AS alias` to the result.
+    pub fn aggregate(mut self, function: Aggregate, column: &str, alias: &str) -> Self {
+        self.columns.push(format!("{}({}) AS {}", function.as_str(), quote_identifier(column), quote_identifier(alias)));
+        self
+    }
+
+    /// Keeps rows whose time is in `range`.
+    pub fn time_range<R>(mut self, range: R) -> Self
+    where
+        R: RangeBounds<DateTime<Utc>>,
+    {
+        for (bound, inclusive, exclusive) in [(range.start_bound(), ">=", ">"), (range.end_bound(), "<=", "<")] {
+            let (op, time) = match bound {
+                Bound::Included(time) => (inclusive, time),
+                Bound::Excluded(time) => (exclusive, time),
+                Bound::Unbounded => continue,
+            };
+            let param = self.param(PointValue::Timestamp(*time));
+            self.filters.push(format!("time {op} {param}"));
+        }
+        self
+    }
+
+    /// Keeps rows where `tag` is `value`.
+    pub fn tag_eq(mut self, tag: &str, value: &str) -> Self {
+        let param = self.param(PointValue::String(value.to_string()));
+        self.filters.push(format!("{} = {param}", quote_identifier(tag)));
+        self
+    }
+
+    /// Keeps rows where `tag` is one of `values`. No values keeps no rows.
+    pub fn tag_in<I, S>(mut self, tag: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let params: Vec<String> = values.into_iter()
+            .map(|value| self.param(PointValue::String(value.as_ref().to_string())))
+            .collect();
+        if params.is_empty() {
+            self.filters.push("FALSE".to_string());
+        } else {
+            self.filters.push(format!("{} IN ({})", quote_identifier(tag), params.join(", ")));
+        }
+        self
+    }
+
+    /// Groups rows into time buckets of `interval`, returned as the `time`
+    /// column. Combine with [`aggregate`](QueryBuilder::aggregate).
+    pub fn date_bin(mut self, interval: Duration) -> Self {
+        let interval = if interval.subsec_nanos() == 0 {
+            format!("{} seconds", interval.as_secs())
+        } else {
+            format!("{} nanoseconds", interval.as_nanos())
+        };
+        let bucket = format!("date_bin(INTERVAL '{interval}', time)");
+        self.columns.push(format!("{bucket} AS \"time\""));
+        self.group_by.push(bucket);
+        self
+    }
+
+    /// Groups rows by `column`, which is also added to the result.
+    pub fn group_by(mut self, column: &str) -> Self {
+        let column = quote_identifier(column);
+        self.columns.push(column.clone());
+        self.group_by.push(column);
+        self
+    }
+
+    pub fn order_by(mut self, column: &str, order: Order) -> Self {
+        let order = match order {
+            Order::Asc => "ASC",
+            Order::Desc => "DESC",
+        };
+        self.order_by.push(format!("{} {order}", quote_identifier(column)));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// The SQL and the parameters it refers to.
+    pub fn build(self) -> (String, Vec<(String, PointValue)>) {
+        let columns = if self.columns.is_empty() { "*".to_string() } else { self.columns.join(", ") };
+        let mut sql = format!("SELECT {columns} FROM {}", quote_identifier(&self.table));
+        if !self.filters.is_empty() {
+            let _ = write!(sql, " WHERE {}", self.filters.join(" AND "));
+        }
+        if !self.group_by.is_empty() {
+            let _ = write!(sql, " GROUP BY {}", self.group_by.join(", "));
+        }
+        if !self.order_by.is_empty() {
+            let _ = write!(sql, " ORDER BY {}", self.order_by.join(", "));
+        }
+        if let Some(limit) = self.limit {
+            let _ = write!(sql, " LIMIT {limit}");
+        }
+        (sql, self.params)
+    }
+
+    /// Adds a parameter and returns the placeholder that refers to it.
+    fn param(&mut self, value: PointValue) -> String {
+        let name = format!("p{}", self.params.len());
+        let placeholder = format!("${name}");
+        self.params.push((name, value));
+        placeholder
+    }
+}
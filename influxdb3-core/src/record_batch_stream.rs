@@ -0,0 +1,305 @@
+use std::future::Future;
+use std::pin::{Pin, pin};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use arrow_array::RecordBatch;
+use arrow_flight::decode::{DecodedPayload, FlightDataDecoder};
+use arrow_schema::{Schema, SchemaRef};
+use futures::{Stream, StreamExt as _};
+use tokio::io::AsyncWrite;
+use tokio::time::Sleep;
+use tokio_util::sync::WaitForCancellationFutureOwned;
+
+use crate::{export, ExportFormat, InfluxDBError, QueryOptions, ResultSchema};
+
+/// The parts of [`QueryOptions`] checked while a query runs.
+#[derive(Debug, Default)]
+pub(crate) struct QueryLimits {
+    deadline: Option<Pin<Box<Sleep>>>,
+    cancelled: Option<Pin<Box<WaitForCancellationFutureOwned>>>,
+    max_rows: Option<usize>,
+    max_bytes: Option<usize>,
+    rows: usize,
+    bytes: usize,
+}
+
+impl QueryLimits {
+    /// `default_timeout` applies if `options` has neither a timeout nor a
+    /// deadline.
+    pub fn new(options: &QueryOptions, default_timeout: Option<Duration>) -> Self {
+        let deadline = options.resolve_deadline(default_timeout);
+        Self {
+            deadline: deadline.map(|deadline| Box::pin(tokio::time::sleep_until(deadline.into()))),
+            cancelled: options.cancellation_token.clone().map(|token| Box::pin(token.cancelled_owned())),
+            max_rows: options.max_rows,
+            max_bytes: options.max_bytes,
+            rows: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Ready with an error once the deadline passes or the query is
+    /// cancelled.
+    fn poll_stop(&mut self, cx: &mut Context<'_>) -> Poll<InfluxDBError> {
+        if let Some(cancelled) = &mut self.cancelled
+            && cancelled.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(InfluxDBError::QueryCancelled);
+        }
+        if let Some(deadline) = &mut self.deadline
+            && deadline.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(InfluxDBError::QueryTimeout);
+        }
+        Poll::Pending
+    }
+
+    /// Runs `future` until it completes, the deadline passes or the query is
+    /// cancelled, whichever comes first.
+    pub async fn run<T>(&mut self, future: impl Future<Output = Result<T, InfluxDBError>>) -> Result<T, InfluxDBError> {
+        let mut future = pin!(future);
+        futures::future::poll_fn(|cx| {
+            if let Poll::Ready(e) = self.poll_stop(cx) {
+                return Poll::Ready(Err(e));
+            }
+            future.as_mut().poll(cx)
+        }).await
+    }
+
+    /// Like `run`, for the call that starts a query. Without a deadline the
+    /// wait for the server to start answering is still bounded by
+    /// `response_timeout`.
+    pub async fn start<T>(&mut self, response_timeout: Duration, future: impl Future<Output = Result<T, InfluxDBError>>) -> Result<T, InfluxDBError> {
+        if self.deadline.is_some() {
+            return self.run(future).await;
+        }
+        self.run(async {
+            tokio::time::timeout(response_timeout, future).await
+                .map_err(|_| InfluxDBError::QueryTimeout)?
+        }).await
+    }
+
+    fn count(&mut self, batch: &RecordBatch) -> Result<(), InfluxDBError> {
+        self.rows += batch.num_rows();
+        self.bytes += batch.get_array_memory_size();
+        if let Some(max_rows) = self.max_rows
+            && self.rows > max_rows {
+            return Err(InfluxDBError::RowLimitExceeded(max_rows));
+        }
+        if let Some(max_bytes) = self.max_bytes
+            && self.bytes > max_bytes {
+            return Err(InfluxDBError::ByteLimitExceeded(max_bytes));
+        }
+        Ok(())
+    }
+}
+
+/// Query results as Arrow record batches, as they arrive from the server.
+///
+/// The schema is known before the first batch is read, and keeps the field
+/// metadata the server sends, such as `iox::column::type`. Dropping the
+/// stream cancels the query.
+#[derive(Debug)]
+pub struct RecordBatchStream {
+    /// `None` once the query has ended, so an error ends the gRPC call
+    /// without waiting for the stream to be dropped.
+    inner: Option<FlightDataDecoder>,
+    schema: SchemaRef,
+    /// A batch that arrived before any schema message.
+    first_batch: Option<RecordBatch>,
+    limits: QueryLimits,
+}
+
+impl RecordBatchStream {
+    /// Reads from `inner` up to the schema message.
+    pub async fn new(inner: FlightDataDecoder) -> Result<Self, InfluxDBError> {
+        Self::with_limits(inner, QueryLimits::default()).await
+    }
+
+    pub(crate) async fn with_limits(mut inner: FlightDataDecoder, mut limits: QueryLimits) -> Result<Self, InfluxDBError> {
+        let (schema, first_batch) = limits.run(async {
+            while let Some(data) = inner.next().await {
+                match data?.payload {
+                    DecodedPayload::Schema(schema) => return Ok((schema, None)),
+                    DecodedPayload::RecordBatch(batch) => return Ok((batch.schema(), Some(batch))),
+                    DecodedPayload::None => {},
+                }
+            }
+            Ok((Arc::new(Schema::empty()), None))
+        }).await?;
+        Ok(Self { inner: Some(inner), schema, first_batch, limits })
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// The schema with the role of each column spelled out.
+    pub fn result_schema(&self) -> ResultSchema {
+        ResultSchema::new(self.schema.clone())
+    }
+
+    /// Writes the rest of the results to `writer` in `format`.
+    pub async fn export<F, W>(self, format: F, writer: &mut W) -> Result<(), InfluxDBError>
+    where
+        F: ExportFormat,
+        W: AsyncWrite + Unpin,
+    {
+        export(self.schema(), self, format, writer).await
+    }
+
+    /// Ends the gRPC call and returns `e`.
+    fn stop(&mut self, e: InfluxDBError) -> Poll<Option<Result<RecordBatch, InfluxDBError>>> {
+        self.inner = None;
+        self.first_batch = None;
+        Poll::Ready(Some(Err(e)))
+    }
+}
+
+impl Stream for RecordBatchStream {
+    type Item = Result<RecordBatch, InfluxDBError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.inner.is_none() {
+            return Poll::Ready(None);
+        }
+        if let Poll::Ready(e) = this.limits.poll_stop(cx) {
+            return this.stop(e);
+        }
+        if let Some(batch) = this.first_batch.take() {
+            if let Err(e) = this.limits.count(&batch) {
+                return this.stop(e);
+            }
+            return Poll::Ready(Some(Ok(batch)));
+        }
+        loop {
+            let Some(inner) = this.inner.as_mut() else {
+                return Poll::Ready(None);
+            };
+            let data = match futures::ready!(inner.poll_next_unpin(cx)) {
+                Some(Ok(data)) => data,
+                Some(Err(e)) => return this.stop(e.into()),
+                None => {
+                    this.inner = None;
+                    return Poll::Ready(None);
+                },
+            };
+            match data.payload {
+                DecodedPayload::RecordBatch(batch) => {
+                    if let Err(e) = this.limits.count(&batch) {
+                        return this.stop(e);
+                    }
+                    return Poll::Ready(Some(Ok(batch)));
+                },
+                DecodedPayload::Schema(schema) => this.schema = schema,
+                DecodedPayload::None => {},
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{ArrayRef, Int64Array};
+    use arrow_flight::encode::FlightDataEncoderBuilder;
+    use futures::stream;
+    use tokio_util::sync::CancellationToken;
+
+    fn batch(values: Vec<i64>) -> RecordBatch {
+        let column: ArrayRef = Arc::new(Int64Array::from(values));
+        RecordBatch::try_from_iter([("v", column)]).unwrap()
+    }
+
+    /// Sends `batches` through Flight encoding, then never ends if `open`.
+    async fn results(batches: Vec<RecordBatch>, open: bool, options: &QueryOptions) -> Result<RecordBatchStream, InfluxDBError> {
+        let batches = stream::iter(batches.into_iter().map(Ok));
+        let batches = if open { batches.chain(stream::pending()).boxed() } else { batches.boxed() };
+        let data = FlightDataEncoderBuilder::new().build(batches);
+        RecordBatchStream::with_limits(FlightDataDecoder::new(data), QueryLimits::new(options, None)).await
+    }
+
+    async fn rows(stream: &mut RecordBatchStream) -> Vec<Result<usize, InfluxDBError>> {
+        stream.map(|batch| batch.map(|batch| batch.num_rows())).collect().await
+    }
+
+    #[tokio::test]
+    async fn row_limit() {
+        let options = QueryOptions::new().max_rows(3);
+        let mut stream = results(vec![batch(vec![1, 2]), batch(vec![3]), batch(vec![4])], false, &options).await.unwrap();
+        let rows = rows(&mut stream).await;
+        assert!(matches!(rows[..], [Ok(2), Ok(1), Err(InfluxDBError::RowLimitExceeded(3))]), "{rows:?}");
+    }
+
+    #[tokio::test]
+    async fn byte_limit() {
+        // Decoded batches take more memory than the ones that were sent.
+        let mut unlimited = results(vec![batch(vec![1, 2])], false, &QueryOptions::new()).await.unwrap();
+        let size = unlimited.next().await.unwrap().unwrap().get_array_memory_size();
+        let options = QueryOptions::new().max_bytes(size);
+        let mut stream = results(vec![batch(vec![1, 2]), batch(vec![3, 4])], false, &options).await.unwrap();
+        let rows = rows(&mut stream).await;
+        assert!(matches!(rows[..], [Ok(2), Err(InfluxDBError::ByteLimitExceeded(n))] if n == size), "{rows:?}");
+    }
+
+    #[tokio::test]
+    async fn deadline_ends_the_results() {
+        let options = QueryOptions::new().timeout(Duration::from_millis(50));
+        let mut stream = results(vec![batch(vec![1])], true, &options).await.unwrap();
+        let rows = rows(&mut stream).await;
+        assert!(matches!(rows[..], [Ok(1), Err(InfluxDBError::QueryTimeout)]), "{rows:?}");
+    }
+
+    #[tokio::test]
+    async fn deadline_covers_waiting_for_the_schema() {
+        let options = QueryOptions::new().timeout(Duration::from_millis(50));
+        let err = results(vec![], true, &options).await.unwrap_err();
+        assert!(matches!(err, InfluxDBError::QueryTimeout), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn cancellation_ends_the_results() {
+        let token = CancellationToken::new();
+        let options = QueryOptions::new().cancellation_token(token.clone());
+        let mut stream = results(vec![batch(vec![1])], true, &options).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap().num_rows(), 1);
+
+        token.cancel();
+        let rows = rows(&mut stream).await;
+        assert!(matches!(rows[..], [Err(InfluxDBError::QueryCancelled)]), "{rows:?}");
+    }
+
+    #[tokio::test]
+    async fn cancelled_before_the_start() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut limits = QueryLimits::new(&QueryOptions::new().cancellation_token(token), None);
+        let result = limits.start(Duration::from_secs(60), futures::future::pending::<Result<(), _>>()).await;
+        assert!(matches!(result, Err(InfluxDBError::QueryCancelled)), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn start_without_a_deadline_waits_for_the_response_timeout() {
+        let mut limits = QueryLimits::default();
+        let result = limits.start(Duration::from_millis(20), futures::future::pending::<Result<(), _>>()).await;
+        assert!(matches!(result, Err(InfluxDBError::QueryTimeout)), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn start_with_a_deadline_ignores_the_response_timeout() {
+        let mut limits = QueryLimits::new(&QueryOptions::new().timeout(Duration::from_secs(60)), None);
+        let slow = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(1)
+        };
+        assert_eq!(limits.start(Duration::from_millis(10), slow).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn default_timeout_applies_without_options() {
+        let mut limits = QueryLimits::new(&QueryOptions::new(), Some(Duration::from_millis(20)));
+        let result = limits.run(futures::future::pending::<Result<(), _>>()).await;
+        assert!(matches!(result, Err(InfluxDBError::QueryTimeout)), "{result:?}");
+    }
+}
@@ -0,0 +1,109 @@
+use arrow_schema::{DataType, Field, SchemaRef};
+
+/// The InfluxDB type of a field column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Integer,
+    UInteger,
+    Float,
+    String,
+    Boolean,
+}
+
+/// What a result column holds, as far as InfluxDB is concerned.
+///
+/// Columns read straight from a table carry an `iox::column::type` entry in
+/// their metadata; computed columns such as `count(*)` have none and are
+/// [`Other`](ColumnRole::Other).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnRole {
+    Tag,
+    Field(FieldType),
+    /// The row timestamp.
+    Timestamp,
+    /// The measurement name, in InfluxQL results and queries that select it.
+    Measurement,
+    Other,
+}
+
+impl ColumnRole {
+    pub fn of(field: &Field) -> Self {
+        let column_type = field.metadata().get("iox::column::type").map(String::as_str);
+        match column_type {
+            Some("iox::column_type::field::integer") => ColumnRole::Field(FieldType::Integer),
+            Some("iox::column_type::field::uinteger") => ColumnRole::Field(FieldType::UInteger),
+            Some("iox::column_type::field::float") => ColumnRole::Field(FieldType::Float),
+            Some("iox::column_type::field::string") => ColumnRole::Field(FieldType::String),
+            Some("iox::column_type::field::boolean") => ColumnRole::Field(FieldType::Boolean),
+            Some("iox::column_type::tag") => ColumnRole::Tag,
+            Some("iox::column_type::timestamp") => ColumnRole::Timestamp,
+            _ => match field.name().as_str() {
+                "time" if matches!(field.data_type(), DataType::Timestamp(_, _)) => ColumnRole::Timestamp,
+                "measurement" | "iox::measurement" => ColumnRole::Measurement,
+                _ => ColumnRole::Other,
+            },
+        }
+    }
+}
+
+/// A result column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub data_type: DataType,
+    pub nullable: bool,
+    pub role: ColumnRole,
+}
+
+/// The columns of a query result, with the role each one plays.
+#[derive(Debug, Clone)]
+pub struct ResultSchema {
+    arrow_schema: SchemaRef,
+    columns: Vec<ColumnInfo>,
+}
+
+impl ResultSchema {
+    pub fn new(arrow_schema: SchemaRef) -> Self {
+        let columns = arrow_schema.fields().iter()
+            .map(|field| ColumnInfo {
+                name: field.name().clone(),
+                data_type: field.data_type().clone(),
+                nullable: field.is_nullable(),
+                role: ColumnRole::of(field),
+            })
+            .collect();
+        Self { arrow_schema, columns }
+    }
+
+    /// The columns in result order.
+    pub fn columns(&self) -> &[ColumnInfo] {
+        &self.columns
+    }
+
+    pub fn column(&self, name: &str) -> Option<&ColumnInfo> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    pub fn tags(&self) -> impl Iterator<Item = &ColumnInfo> {
+        self.columns.iter().filter(|column| column.role == ColumnRole::Tag)
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = &ColumnInfo> {
+        self.columns.iter().filter(|column| matches!(column.role, ColumnRole::Field(_)))
+    }
+
+    pub fn time_column(&self) -> Option<&ColumnInfo> {
+        self.columns.iter().find(|column| column.role == ColumnRole::Timestamp)
+    }
+
+    /// The schema as the server sent it, metadata included.
+    pub fn arrow_schema(&self) -> SchemaRef {
+        self.arrow_schema.clone()
+    }
+}
+
+impl From<SchemaRef> for ResultSchema {
+    fn from(arrow_schema: SchemaRef) -> Self {
+        Self::new(arrow_schema)
+    }
+}
@@ -0,0 +1,203 @@
+use std::future::Future;
+use std::hash::{BuildHasher as _, Hasher as _, RandomState};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::InfluxDBError;
+
+type RetryPredicate = Arc<dyn Fn(&InfluxDBError) -> bool + Send + Sync>;
+
+/// Controls how failed write requests are retried.
+///
+/// Each request is attempted up to `max_attempts` times. The delay before
+/// retry `n` is `base_delay * 2^(n-1)`, capped at `max_delay` and reduced by
+/// up to `jitter` (a fraction between 0 and 1) at random. A `Retry-After`
+/// sent by the server takes precedence over the computed delay, but is also
+/// capped at `max_delay`.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+    retryable: RetryPredicate,
+}
+
+impl std::fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            retryable: Arc::new(InfluxDBError::is_transient),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Total number of attempts per request, including the first one.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Decides which errors are worth retrying. Defaults to
+    /// [`InfluxDBError::is_transient`].
+    pub fn retryable<F>(mut self, retryable: F) -> Self
+    where
+        F: Fn(&InfluxDBError) -> bool + Send + Sync + 'static,
+    {
+        self.retryable = Arc::new(retryable);
+        self
+    }
+
+    pub fn is_retryable(&self, err: &InfluxDBError) -> bool {
+        (self.retryable)(err)
+    }
+
+    fn delay(&self, retry: u32, err: &InfluxDBError) -> Duration {
+        if let InfluxDBError::RateLimited(secs) = err
+            && *secs > 0 {
+            return Duration::from_secs(*secs).min(self.max_delay);
+        }
+
+        let exp = self.base_delay.saturating_mul(1 << (retry - 1).min(31));
+        let delay = exp.min(self.max_delay);
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        delay.mul_f64(1.0 - self.jitter * random)
+    }
+
+    pub(crate) async fn run<F, Fut, T>(&self, mut request: F) -> Result<T, InfluxDBError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, InfluxDBError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match request().await {
+                Err(e) if attempt < self.max_attempts && self.is_retryable(&e) => {
+                    tokio::time::sleep(self.delay(attempt, &e)).await;
+                    attempt += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new()
+            .base_delay(Duration::from_millis(100))
+            .max_delay(Duration::from_secs(1))
+            .jitter(0.0)
+    }
+
+    fn server_error() -> InfluxDBError {
+        InfluxDBError::ServerError(503, "unavailable".to_string())
+    }
+
+    #[test]
+    fn delay_grows_exponentially_up_to_max_delay() {
+        let policy = policy();
+        let delays: Vec<_> = (1..=6).map(|retry| policy.delay(retry, &server_error())).collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000].map(Duration::from_millis));
+        assert_eq!(policy.delay(u32::MAX, &server_error()), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter_only_shortens_the_delay() {
+        let policy = policy().jitter(0.5);
+        for _ in 0..1000 {
+            let delay = policy.delay(3, &server_error());
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400), "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn retry_after_overrides_the_delay() {
+        let policy = policy().max_delay(Duration::from_secs(10)).jitter(1.0);
+        assert_eq!(policy.delay(1, &InfluxDBError::RateLimited(3)), Duration::from_secs(3));
+        // Without a usable Retry-After the usual backoff applies.
+        assert!(policy.delay(1, &InfluxDBError::RateLimited(0)) <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn retry_after_is_capped_at_max_delay() {
+        assert_eq!(policy().delay(1, &InfluxDBError::RateLimited(86_400)), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn transient_errors() {
+        assert!(InfluxDBError::RateLimited(1).is_transient());
+        for status in [500, 502, 503, 504] {
+            assert!(InfluxDBError::ServerError(status, String::new()).is_transient(), "{status}");
+        }
+        for status in [501, 505] {
+            assert!(!InfluxDBError::ServerError(status, String::new()).is_transient(), "{status}");
+        }
+        assert!(!InfluxDBError::ApiError("bad request".to_string()).is_transient());
+        assert!(!InfluxDBError::MissingToken.is_transient());
+        assert!(!InfluxDBError::SpoolFull.is_transient());
+    }
+
+    #[tokio::test]
+    async fn run_retries_only_retryable_errors() {
+        let policy = policy().base_delay(Duration::ZERO).max_attempts(3);
+
+        let mut attempts = 0;
+        let result: Result<(), _> = policy.run(|| {
+            attempts += 1;
+            async { Err(server_error()) }
+        }).await;
+        assert!(matches!(result, Err(InfluxDBError::ServerError(503, _))));
+        assert_eq!(attempts, 3);
+
+        let mut attempts = 0;
+        let result: Result<(), _> = policy.run(|| {
+            attempts += 1;
+            async { Err(InfluxDBError::ApiError("bad request".to_string())) }
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+}
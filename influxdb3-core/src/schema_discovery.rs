@@ -0,0 +1,126 @@
+use std::ops::{Bound, RangeBounds};
+
+use arrow_array::RecordBatch;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt as _;
+
+use crate::query_builder::quote_identifier;
+use crate::{Client, ColumnInfo, ColumnRole, DecodeColumn, FieldType, InfluxDBError, PointValue, RecordBatchStream};
+
+/// A field column of a table and its type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldKey {
+    pub name: String,
+    pub field_type: FieldType,
+}
+
+impl Client {
+    /// The tables (measurements) in the database.
+    pub async fn list_tables(&self) -> Result<Vec<String>, InfluxDBError> {
+        let batches = self.query_arrow(
+            "SELECT table_name FROM information_schema.tables WHERE table_schema = 'iox' ORDER BY table_name",
+        ).await?;
+        strings(batches).await
+    }
+
+    /// The columns of `table` in table order. Names and nullability come
+    /// from `information_schema.columns`, and the type and role of each
+    /// column from the schema of `SELECT * FROM <table> LIMIT 0`, whose
+    /// columns carry their IOx column type. Empty if there is no such table.
+    pub async fn list_columns(&self, table: &str) -> Result<Vec<ColumnInfo>, InfluxDBError> {
+        let batches = self.query_arrow_with_params(
+            "SELECT column_name, is_nullable FROM information_schema.columns \
+             WHERE table_schema = 'iox' AND table_name = $table ORDER BY ordinal_position",
+            [("table", table)],
+        ).await?;
+        let columns: Vec<(String, bool)> = batches
+            .map_ok(|batch| futures::stream::iter(column_names(&batch)))
+            .try_flatten()
+            .try_collect()
+            .await?;
+        if columns.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let schema = self.query_arrow(&format!("SELECT * FROM {} LIMIT 0", quote_identifier(table))).await?
+            .result_schema();
+        Ok(columns.into_iter()
+            .filter_map(|(name, nullable)| {
+                let column = schema.column(&name)?;
+                Some(ColumnInfo { nullable, ..column.clone() })
+            })
+            .collect())
+    }
+
+    /// The tag keys of `table`.
+    pub async fn tag_keys(&self, table: &str) -> Result<Vec<String>, InfluxDBError> {
+        Ok(self.list_columns(table).await?.into_iter()
+            .filter(|column| column.role == ColumnRole::Tag)
+            .map(|column| column.name)
+            .collect())
+    }
+
+    /// The field keys of `table` and their types.
+    pub async fn field_keys(&self, table: &str) -> Result<Vec<FieldKey>, InfluxDBError> {
+        Ok(self.list_columns(table).await?.into_iter()
+            .filter_map(|column| match column.role {
+                ColumnRole::Field(field_type) => Some(FieldKey { name: column.name, field_type }),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// The distinct values of tag `tag` in `table` for rows with a time in
+    /// `time_range`, sorted. Pass `..` to look at all rows.
+    pub async fn tag_values<R>(&self, table: &str, tag: &str, time_range: R) -> Result<Vec<String>, InfluxDBError>
+    where
+        R: RangeBounds<DateTime<Utc>>,
+    {
+        let tag = quote_identifier(tag);
+        let mut query = format!("SELECT DISTINCT {tag} FROM {} WHERE {tag} IS NOT NULL", quote_identifier(table));
+        let mut params = Vec::new();
+        for (bound, inclusive, exclusive, name) in [
+            (time_range.start_bound(), ">=", ">", "start"),
+            (time_range.end_bound(), "<=", "<", "end"),
+        ] {
+            let (op, time) = match bound {
+                Bound::Included(time) => (inclusive, time),
+                Bound::Excluded(time) => (exclusive, time),
+                Bound::Unbounded => continue,
+            };
+            query.push_str(&format!(" AND time {op} ${name}"));
+            params.push((name, PointValue::Timestamp(*time)));
+        }
+        query.push_str(&format!(" ORDER BY {tag}"));
+
+        strings(self.query_arrow_with_params(&query, params).await?).await
+    }
+}
+
+/// Reads the `column_name` and `is_nullable` columns of an
+/// `information_schema.columns` result.
+fn column_names(batch: &RecordBatch) -> Vec<Result<(String, bool), InfluxDBError>> {
+    let schema = batch.schema_ref();
+    let [names, nullable] = [0, 1]
+        .map(|i| String::decode_column(batch.column(i).as_ref(), schema.field(i).name()));
+    names.into_iter().zip(nullable)
+        .map(|(name, nullable)| {
+            let name = name?.ok_or_else(|| InfluxDBError::NullValue("column_name".to_string()))?;
+            Ok((name, nullable?.as_deref() == Some("YES")))
+        })
+        .collect()
+}
+
+/// The values of the first column of `batches`, skipping nulls.
+async fn strings(batches: RecordBatchStream) -> Result<Vec<String>, InfluxDBError> {
+    let name = match batches.schema().fields().first() {
+        Some(field) => field.name().clone(),
+        None => return Ok(Vec::new()),
+    };
+    batches
+        .map_ok(|batch| futures::stream::iter(String::decode_column(batch.column(0).as_ref(), &name)))
+        .try_flatten()
+        .try_filter_map(futures::future::ok)
+        .try_collect()
+        .await
+}
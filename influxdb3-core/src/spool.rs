@@ -0,0 +1,613 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read as _, Seek as _, SeekFrom, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+
+use crate::InfluxDBError;
+use crate::client::WriteRequest;
+
+const SEGMENT_EXTENSION: &str = "seg";
+const CURSOR_FILE: &str = "cursor";
+// payload length (u32 LE) + crc32 of the payload (u32 LE)
+const RECORD_HEADER_LEN: u64 = 8;
+
+/// Which data to give up when the spool reaches its size cap.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpoolOverflow {
+    /// Delete the oldest segment to make room.
+    #[default]
+    DropOldest,
+    /// Refuse to spool the new batch; its write fails with the original error.
+    DropNewest,
+}
+
+/// Settings for the on-disk write spool.
+///
+/// When a batch cannot be delivered because of a transient error (see
+/// [`RetryPolicy`](crate::RetryPolicy)), it is appended to a segment file in
+/// `dir` and the write reports success. Spooled batches are sent, oldest
+/// first, before any newer batch and by [`Client::drain_spool`](crate::Client::drain_spool).
+/// A spooled batch the server refuses with an error that is not retryable is
+/// dropped rather than holding up the ones behind it.
+///
+/// Every record is checksummed and synced to disk before the write returns.
+/// A torn record at the end of a segment, left by a crash, is discarded when
+/// the spool is reopened. Delivery is at-least-once: a batch sent right
+/// before a crash may be sent again after the restart.
+#[derive(Debug, Clone)]
+pub struct SpoolOptions {
+    dir: PathBuf,
+    max_bytes: u64,
+    segment_bytes: u64,
+    overflow: SpoolOverflow,
+}
+
+impl SpoolOptions {
+    pub fn new<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            dir: dir.into(),
+            max_bytes: 1024 * 1024 * 1024,
+            segment_bytes: 16 * 1024 * 1024,
+            overflow: SpoolOverflow::DropOldest,
+        }
+    }
+
+    /// Upper bound on the total size of all segment files.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// A new segment file is started once the current one reaches this size.
+    pub fn segment_bytes(mut self, segment_bytes: u64) -> Self {
+        self.segment_bytes = segment_bytes.max(1);
+        self
+    }
+
+    pub fn overflow(mut self, overflow: SpoolOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+/// What a call to [`Client::drain_spool`](crate::Client::drain_spool) did.
+#[derive(Debug, Default)]
+pub struct DrainReport {
+    /// Spooled batches delivered, including ones the server accepted in part.
+    pub sent: usize,
+    /// Why spooled data was dropped since the last drain: a batch refused
+    /// with an error that is not retryable, or the lines of a partial write.
+    /// Drops by the writes that send the spool ahead of a new batch are
+    /// included.
+    pub dropped: Vec<InfluxDBError>,
+}
+
+pub(crate) struct Record {
+    pub request: WriteRequest,
+    pub body: Bytes,
+    /// Where the record starts, so `advance` can tell whether it is still
+    /// the next one.
+    segment: u64,
+    offset: u64,
+    len: u64,
+}
+
+pub(crate) struct Spool {
+    state: Mutex<State>,
+    /// Held while draining so batches go out one at a time and in order.
+    pub drain_lock: tokio::sync::Mutex<()>,
+    /// Errors of the batches dropped since the last `take_dropped`.
+    dropped: Mutex<Vec<InfluxDBError>>,
+}
+
+impl Spool {
+    pub fn open(options: SpoolOptions) -> Result<Self, InfluxDBError> {
+        Ok(Spool {
+            state: Mutex::new(State::open(options)?),
+            drain_lock: tokio::sync::Mutex::new(()),
+            dropped: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.state.lock().unwrap().has_pending()
+    }
+
+    pub fn record_dropped(&self, err: InfluxDBError) {
+        self.dropped.lock().unwrap().push(err);
+    }
+
+    pub fn take_dropped(&self) -> Vec<InfluxDBError> {
+        std::mem::take(&mut self.dropped.lock().unwrap())
+    }
+
+    // The file operations below block on fsync, so they run on the blocking
+    // thread pool.
+
+    pub async fn append(self: &Arc<Self>, request: &WriteRequest, body: Bytes) -> Result<(), InfluxDBError> {
+        let header = serde_json::to_vec(request)?;
+        let spool = self.clone();
+        tokio::task::spawn_blocking(move || spool.state.lock().unwrap().append(&header, &body))
+            .await
+            .map_err(|e| InfluxDBError::Other(e.to_string()))?
+    }
+
+    pub async fn peek(self: &Arc<Self>) -> Result<Option<Record>, InfluxDBError> {
+        let spool = self.clone();
+        tokio::task::spawn_blocking(move || spool.state.lock().unwrap().peek())
+            .await
+            .map_err(|e| InfluxDBError::Other(e.to_string()))?
+    }
+
+    /// Moves past `record`, unless an append already dropped it to make room.
+    pub async fn advance(self: &Arc<Self>, record: &Record) -> Result<(), InfluxDBError> {
+        let spool = self.clone();
+        let (segment, offset, len) = (record.segment, record.offset, record.len);
+        tokio::task::spawn_blocking(move || spool.state.lock().unwrap().advance(segment, offset, len))
+            .await
+            .map_err(|e| InfluxDBError::Other(e.to_string()))?
+    }
+}
+
+struct Segment {
+    id: u64,
+    len: u64,
+}
+
+/// Segments are read from the front and appended to at the back. The read
+/// position is always inside the front segment and is persisted in the
+/// cursor file.
+struct State {
+    options: SpoolOptions,
+    segments: VecDeque<Segment>,
+    writer: Option<File>,
+    read_offset: u64,
+    next_id: u64,
+}
+
+impl State {
+    fn open(options: SpoolOptions) -> Result<Self, InfluxDBError> {
+        fs::create_dir_all(&options.dir)?;
+
+        let mut ids = vec![];
+        for entry in fs::read_dir(&options.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            if let Some(id) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse::<u64>().ok()) {
+                ids.push(id);
+            }
+        }
+        ids.sort_unstable();
+
+        let (cursor_id, cursor_offset) = read_cursor(&options.dir)?;
+        let mut segments = VecDeque::new();
+        for id in ids {
+            let path = segment_path(&options.dir, id);
+            if id < cursor_id {
+                fs::remove_file(path)?;
+                continue;
+            }
+            segments.push_back(Segment { id, len: fs::metadata(path)?.len() });
+        }
+
+        let read_offset = match segments.front() {
+            Some(front) if front.id == cursor_id => cursor_offset.min(front.len),
+            _ => 0,
+        };
+        let next_id = segments.back().map_or(cursor_id, |s| s.id) + 1;
+
+        let mut state = State { options, segments, writer: None, read_offset, next_id };
+        state.truncate_torn_tail()?;
+        Ok(state)
+    }
+
+    /// Cuts the last segment after its last intact record.
+    fn truncate_torn_tail(&mut self) -> Result<(), InfluxDBError> {
+        let single = self.segments.len() == 1;
+        let Some(last) = self.segments.back_mut() else {
+            return Ok(());
+        };
+        let path = segment_path(&self.options.dir, last.id);
+        let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let mut offset = if single { self.read_offset } else { 0 };
+        file.seek(SeekFrom::Start(offset))?;
+        while let Some(len) = read_record(&mut file)?.map(|(_, len)| len) {
+            offset += len;
+        }
+        if offset < last.len {
+            file.set_len(offset)?;
+            file.sync_all()?;
+            last.len = offset;
+        }
+        Ok(())
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.segments.iter().map(|s| s.len).sum()
+    }
+
+    fn has_pending(&self) -> bool {
+        self.total_bytes() > self.read_offset
+    }
+
+    fn append(&mut self, header: &[u8], body: &[u8]) -> Result<(), InfluxDBError> {
+        let mut payload = Vec::with_capacity(4 + header.len() + body.len());
+        payload.extend((header.len() as u32).to_le_bytes());
+        payload.extend(header);
+        payload.extend(body);
+        let record_len = RECORD_HEADER_LEN + payload.len() as u64;
+
+        if record_len > self.options.max_bytes {
+            return Err(InfluxDBError::SpoolFull);
+        }
+        while self.total_bytes() + record_len > self.options.max_bytes {
+            match self.options.overflow {
+                SpoolOverflow::DropNewest => return Err(InfluxDBError::SpoolFull),
+                SpoolOverflow::DropOldest => self.remove_front()?,
+            }
+        }
+
+        let rotate = self.segments.back().is_none_or(|s| s.len >= self.options.segment_bytes);
+        if rotate {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.writer = Some(File::create(segment_path(&self.options.dir, id))?);
+            sync_dir(&self.options.dir)?;
+            self.segments.push_back(Segment { id, len: 0 });
+        }
+        let last = self.segments.back_mut().expect("segment exists after rotation");
+        if self.writer.is_none() {
+            self.writer = Some(OpenOptions::new().append(true).open(segment_path(&self.options.dir, last.id))?);
+        }
+        let writer = self.writer.as_mut().expect("writer is open");
+
+        let mut record = Vec::with_capacity(record_len as usize);
+        record.extend((payload.len() as u32).to_le_bytes());
+        record.extend(crc32fast::hash(&payload).to_le_bytes());
+        record.extend(payload);
+        let written = writer.write_all(&record).and_then(|_| writer.sync_data());
+        if let Err(e) = written {
+            // Don't leave a partial record behind for the next append to
+            // write after.
+            let _ = writer.set_len(last.len);
+            return Err(e.into());
+        }
+        last.len += record_len;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Result<Option<Record>, InfluxDBError> {
+        loop {
+            let Some(front) = self.segments.front() else {
+                return Ok(None);
+            };
+            if self.read_offset >= front.len {
+                if self.segments.len() == 1 {
+                    return Ok(None);
+                }
+                self.remove_front()?;
+                continue;
+            }
+
+            let mut file = File::open(segment_path(&self.options.dir, front.id))?;
+            file.seek(SeekFrom::Start(self.read_offset))?;
+            let Some((payload, len)) = read_record(&mut file)? else {
+                // Nothing readable past this point in the segment.
+                if self.segments.len() == 1 {
+                    return Ok(None);
+                }
+                self.remove_front()?;
+                continue;
+            };
+
+            let header_len = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+            let request = serde_json::from_slice(&payload[4..4 + header_len])?;
+            let body = Bytes::copy_from_slice(&payload[4 + header_len..]);
+            return Ok(Some(Record { request, body, segment: front.id, offset: self.read_offset, len }));
+        }
+    }
+
+    fn advance(&mut self, segment: u64, offset: u64, len: u64) -> Result<(), InfluxDBError> {
+        let is_next = self.segments.front().is_some_and(|front| front.id == segment) && self.read_offset == offset;
+        if !is_next {
+            return Ok(());
+        }
+        self.read_offset += len;
+        match self.segments.front() {
+            Some(front) if self.read_offset >= front.len && self.segments.len() > 1 => self.remove_front(),
+            Some(front) => write_cursor(&self.options.dir, front.id, self.read_offset),
+            None => Ok(()),
+        }
+    }
+
+    fn remove_front(&mut self) -> Result<(), InfluxDBError> {
+        let Some(front) = self.segments.pop_front() else {
+            return Ok(());
+        };
+        if self.segments.is_empty() {
+            self.writer = None;
+        }
+        self.read_offset = 0;
+        let next_id = self.segments.front().map_or(self.next_id, |s| s.id);
+        write_cursor(&self.options.dir, next_id, 0)?;
+        fs::remove_file(segment_path(&self.options.dir, front.id))?;
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{id:020}.{SEGMENT_EXTENSION}"))
+}
+
+/// Reads the record at the current position. Returns `None` at the end of the
+/// file or if the record is truncated or fails its checksum.
+fn read_record(file: &mut File) -> Result<Option<(Vec<u8>, u64)>, InfluxDBError> {
+    let mut header = [0u8; RECORD_HEADER_LEN as usize];
+    if let Err(e) = file.read_exact(&mut header) {
+        return match e.kind() {
+            io::ErrorKind::UnexpectedEof => Ok(None),
+            _ => Err(e.into()),
+        };
+    }
+    let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+    let crc = u32::from_le_bytes(header[4..].try_into().unwrap());
+
+    let mut payload = Vec::new();
+    file.take(len as u64).read_to_end(&mut payload)?;
+    if payload.len() != len || payload.len() < 4 || crc32fast::hash(&payload) != crc {
+        return Ok(None);
+    }
+    let header_len = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+    if 4 + header_len > payload.len() {
+        return Ok(None);
+    }
+    Ok(Some((payload, RECORD_HEADER_LEN + len as u64)))
+}
+
+fn read_cursor(dir: &Path) -> Result<(u64, u64), InfluxDBError> {
+    let contents = match fs::read_to_string(dir.join(CURSOR_FILE)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(e.into()),
+    };
+    let mut parts = contents.split_whitespace().map(str::parse::<u64>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(id)), Some(Ok(offset))) => Ok((id, offset)),
+        _ => Ok((0, 0)),
+    }
+}
+
+/// Replaces the cursor file atomically so a crash leaves either the old or the
+/// new position.
+fn write_cursor(dir: &Path, id: u64, offset: u64) -> Result<(), InfluxDBError> {
+    let tmp = dir.join(format!("{CURSOR_FILE}.tmp"));
+    let mut file = File::create(&tmp)?;
+    file.write_all(format!("{id} {offset}").as_bytes())?;
+    file.sync_all()?;
+    fs::rename(tmp, dir.join(CURSOR_FILE))?;
+    sync_dir(dir)
+}
+
+/// Makes files created in or renamed into `dir` survive a crash.
+fn sync_dir(dir: &Path) -> Result<(), InfluxDBError> {
+    // Directories can't be opened as files on Windows, where the rename and
+    // create are durable without this.
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimestampPrecision;
+
+    fn request() -> WriteRequest {
+        WriteRequest {
+            database: "db".to_string(),
+            precision: TimestampPrecision::Nanoseconds,
+            no_sync: false,
+            accept_partial: true,
+            gzip: false,
+        }
+    }
+
+    fn append(state: &mut State, body: &str) -> Result<(), InfluxDBError> {
+        state.append(&serde_json::to_vec(&request()).unwrap(), body.as_bytes())
+    }
+
+    /// Reads and advances past every pending record.
+    fn drain(state: &mut State) -> Vec<String> {
+        let mut bodies = vec![];
+        while let Some(record) = state.peek().unwrap() {
+            bodies.push(String::from_utf8(record.body.to_vec()).unwrap());
+            state.advance(record.segment, record.offset, record.len).unwrap();
+        }
+        bodies
+    }
+
+    fn segment_files(dir: &Path) -> usize {
+        fs::read_dir(dir).unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().extension().is_some_and(|e| e == SEGMENT_EXTENSION))
+            .count()
+    }
+
+    #[test]
+    fn records_come_back_in_order_across_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = State::open(SpoolOptions::new(dir.path()).segment_bytes(100)).unwrap();
+        let bodies: Vec<String> = (0..20).map(|i| format!("m v={i}i {i}")).collect();
+        for body in &bodies {
+            append(&mut state, body).unwrap();
+        }
+        assert!(segment_files(dir.path()) > 1);
+
+        assert_eq!(drain(&mut state), bodies);
+        assert!(!state.has_pending());
+        // Fully read segments are deleted, except the one still appended to.
+        assert_eq!(segment_files(dir.path()), 1);
+    }
+
+    #[test]
+    fn cursor_survives_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = SpoolOptions::new(dir.path()).segment_bytes(100);
+        let mut state = State::open(options.clone()).unwrap();
+        for i in 0..6 {
+            append(&mut state, &format!("m v={i}i")).unwrap();
+        }
+        for _ in 0..4 {
+            let record = state.peek().unwrap().unwrap();
+            state.advance(record.segment, record.offset, record.len).unwrap();
+        }
+        drop(state);
+
+        let mut state = State::open(options.clone()).unwrap();
+        assert_eq!(drain(&mut state), ["m v=4i", "m v=5i"]);
+        drop(state);
+
+        let mut state = State::open(options).unwrap();
+        assert!(drain(&mut state).is_empty());
+    }
+
+    #[test]
+    fn torn_tail_record_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = SpoolOptions::new(dir.path());
+        let mut state = State::open(options.clone()).unwrap();
+        append(&mut state, "m v=1i").unwrap();
+        append(&mut state, "m v=2i").unwrap();
+        let id = state.segments.back().unwrap().id;
+        let intact_len = state.segments.back().unwrap().len;
+        drop(state);
+
+        // A crash in the middle of writing a third record.
+        let path = segment_path(dir.path(), id);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[200, 0, 0, 0, 1, 2, 3, 4, b'{']).unwrap();
+        drop(file);
+
+        let mut state = State::open(options).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), intact_len);
+        append(&mut state, "m v=3i").unwrap();
+        assert_eq!(drain(&mut state), ["m v=1i", "m v=2i", "m v=3i"]);
+    }
+
+    #[test]
+    fn corrupt_record_fails_its_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = SpoolOptions::new(dir.path());
+        let mut state = State::open(options.clone()).unwrap();
+        append(&mut state, "m v=1i").unwrap();
+        append(&mut state, "m v=2i").unwrap();
+        let id = state.segments.back().unwrap().id;
+        drop(state);
+
+        // Flip the last byte of the second record.
+        let path = segment_path(dir.path(), id);
+        let mut data = fs::read(&path).unwrap();
+        *data.last_mut().unwrap() ^= 0xff;
+        fs::write(&path, data).unwrap();
+
+        let mut state = State::open(options).unwrap();
+        assert_eq!(drain(&mut state), ["m v=1i"]);
+    }
+
+    #[test]
+    fn drop_oldest_makes_room_by_deleting_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let record_len = {
+            let mut state = State::open(SpoolOptions::new(dir.path().join("probe"))).unwrap();
+            append(&mut state, "m v=0i").unwrap();
+            state.total_bytes()
+        };
+        let options = SpoolOptions::new(dir.path().join("spool"))
+            .segment_bytes(record_len * 2)
+            .max_bytes(record_len * 4)
+            .overflow(SpoolOverflow::DropOldest);
+        let mut state = State::open(options).unwrap();
+        for i in 0..6 {
+            append(&mut state, &format!("m v={i}i")).unwrap();
+        }
+        assert!(state.total_bytes() <= record_len * 4);
+        assert_eq!(drain(&mut state), ["m v=2i", "m v=3i", "m v=4i", "m v=5i"]);
+    }
+
+    #[test]
+    fn drop_newest_refuses_new_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let record_len = {
+            let mut state = State::open(SpoolOptions::new(dir.path().join("probe"))).unwrap();
+            append(&mut state, "m v=0i").unwrap();
+            state.total_bytes()
+        };
+        let options = SpoolOptions::new(dir.path().join("spool"))
+            .segment_bytes(record_len * 2)
+            .max_bytes(record_len * 4)
+            .overflow(SpoolOverflow::DropNewest);
+        let mut state = State::open(options).unwrap();
+        for i in 0..4 {
+            append(&mut state, &format!("m v={i}i")).unwrap();
+        }
+        assert!(matches!(append(&mut state, "m v=4i"), Err(InfluxDBError::SpoolFull)));
+        assert_eq!(drain(&mut state), ["m v=0i", "m v=1i", "m v=2i", "m v=3i"]);
+    }
+
+    #[test]
+    fn record_larger_than_the_spool_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = State::open(SpoolOptions::new(dir.path()).max_bytes(16)).unwrap();
+        assert!(matches!(append(&mut state, "m v=1i 1700000000000000000"), Err(InfluxDBError::SpoolFull)));
+        assert!(!state.has_pending());
+    }
+
+    #[test]
+    fn advance_after_the_record_was_dropped_does_not_move_the_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        let record_len = {
+            let mut state = State::open(SpoolOptions::new(dir.path().join("probe"))).unwrap();
+            append(&mut state, "m v=0i").unwrap();
+            state.total_bytes()
+        };
+        let options = SpoolOptions::new(dir.path().join("spool"))
+            .segment_bytes(record_len * 2)
+            .max_bytes(record_len * 4)
+            .overflow(SpoolOverflow::DropOldest);
+        let mut state = State::open(options).unwrap();
+        for i in 0..4 {
+            append(&mut state, &format!("m v={i}i")).unwrap();
+        }
+
+        // The drain peeks the oldest record, and while it is being sent an
+        // append drops its segment to make room.
+        let sending = state.peek().unwrap().unwrap();
+        append(&mut state, "m v=4i").unwrap();
+        state.advance(sending.segment, sending.offset, sending.len).unwrap();
+
+        assert_eq!(drain(&mut state), ["m v=2i", "m v=3i", "m v=4i"]);
+    }
+
+    #[test]
+    fn advance_twice_over_the_same_record_moves_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = State::open(SpoolOptions::new(dir.path())).unwrap();
+        append(&mut state, "m v=1i").unwrap();
+        append(&mut state, "m v=2i").unwrap();
+
+        let record = state.peek().unwrap().unwrap();
+        state.advance(record.segment, record.offset, record.len).unwrap();
+        state.advance(record.segment, record.offset, record.len).unwrap();
+        assert_eq!(drain(&mut state), ["m v=2i"]);
+    }
+}
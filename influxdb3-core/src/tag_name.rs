@@ -4,11 +4,21 @@ use std::ops::Deref;
 use crate::util::validate_name;
 use crate::InfluxDBError;
 
+/// Tags keyed by name. Lines are serialized with their tags sorted, in the
+/// order InfluxDB prefers.
 pub type TagMap = HashMap<TagName, String>;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TagName(String);
 
+impl TagName {
+    /// Tag keys read back from line protocol can be anything the server
+    /// accepts, not only names `TryFrom` allows.
+    pub(crate) fn new_unchecked(name: String) -> Self {
+        TagName(name)
+    }
+}
+
 impl TryFrom<&str> for TagName {
     type Error = InfluxDBError;
 
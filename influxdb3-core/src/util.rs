@@ -1,5 +1,5 @@
 pub(crate) fn validate_name(name: &str) -> bool {
     !name.is_empty() &&
-        name.bytes().next().map_or(false, |b| b.is_ascii_alphanumeric()) &&
+        name.bytes().next().is_some_and(|b| b.is_ascii_alphanumeric()) &&
         name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
-}
\ No newline at end of file
+}
@@ -0,0 +1,358 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::{FutureExt as _, Sink};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::PollSender;
+
+use crate::{Client, InfluxDBError, Point, TagMap, ToPoint, WriteOptions};
+use crate::batch_writer::Batcher;
+
+type ErrorHandler = Arc<dyn Fn(InfluxDBError) + Send + Sync>;
+
+/// Settings for a [`WriteApi`].
+#[derive(Clone)]
+pub struct WriteApiOptions {
+    max_lines: usize,
+    max_bytes: usize,
+    flush_interval: Duration,
+    channel_capacity: usize,
+    write_options: WriteOptions,
+    error_handler: Option<ErrorHandler>,
+}
+
+impl Default for WriteApiOptions {
+    fn default() -> Self {
+        Self {
+            max_lines: 5_000,
+            max_bytes: 1024 * 1024,
+            flush_interval: Duration::from_secs(1),
+            channel_capacity: 10_000,
+            write_options: WriteOptions::default(),
+            error_handler: None,
+        }
+    }
+}
+
+impl WriteApiOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flush once this many lines are buffered.
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = max_lines.max(1);
+        self
+    }
+
+    /// Flush once the buffered line protocol reaches this many bytes.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes.max(1);
+        self
+    }
+
+    /// Flush whatever is buffered at least this often.
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Number of points that can be queued before `write` waits for the
+    /// background task to catch up.
+    pub fn channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = channel_capacity.max(1);
+        self
+    }
+
+    pub fn default_tags(mut self, default_tags: TagMap) -> Self {
+        self.write_options = self.write_options.default_tags(default_tags);
+        self
+    }
+
+    /// Options used for every batch the writer sends. The batch limits in
+    /// here cap the size of each request, and filling a request also
+    /// triggers a flush; use `max_lines` and `max_bytes` to flush earlier.
+    pub fn write_options(mut self, write_options: WriteOptions) -> Self {
+        self.write_options = write_options;
+        self
+    }
+
+    /// Called with every error hit by a flush that nobody is waiting on,
+    /// i.e. one triggered by the timer or a size threshold, and with the
+    /// errors after the first of a flush that is waited on. Without a
+    /// handler the most recent such error is returned by the next `flush` or
+    /// `close` instead.
+    pub fn error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(InfluxDBError) + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Arc::new(handler));
+        self
+    }
+}
+
+enum Message {
+    Point(Point),
+    Flush(oneshot::Sender<Result<(), InfluxDBError>>),
+    Close(oneshot::Sender<Result<(), InfluxDBError>>),
+}
+
+/// A cloneable handle to a background task that buffers points and writes
+/// them in batches.
+///
+/// The buffer is flushed when it reaches the configured line or byte count,
+/// on every flush interval, on [`WriteApi::flush`], and when the handle is
+/// closed. Dropping the last handle flushes the remaining points in the
+/// background. The handle must be created inside a tokio runtime.
+///
+/// `WriteApi` also implements [`Sink`] for any [`ToPoint`] type. Flushing or
+/// closing the sink waits for the points sent so far to be written, like
+/// [`WriteApi::flush`]. Closing the sink does not stop the background task
+/// for other handles.
+pub struct WriteApi {
+    tx: mpsc::Sender<Message>,
+    sink: PollSender<Message>,
+    /// Reply to the flush requested by `poll_flush`, while it is in progress.
+    flushing: Option<oneshot::Receiver<Result<(), InfluxDBError>>>,
+}
+
+impl Clone for WriteApi {
+    fn clone(&self) -> Self {
+        Self::from_sender(self.tx.clone())
+    }
+}
+
+impl WriteApi {
+    pub(crate) fn new(client: Client, options: WriteApiOptions) -> Self {
+        let (tx, rx) = mpsc::channel(options.channel_capacity);
+        tokio::spawn(run(client, options, rx));
+        Self::from_sender(tx)
+    }
+
+    fn from_sender(tx: mpsc::Sender<Message>) -> Self {
+        Self { sink: PollSender::new(tx.clone()), tx, flushing: None }
+    }
+
+    /// Queues a point to be written.
+    pub async fn write<T>(&self, point: T) -> Result<(), InfluxDBError>
+    where
+        T: ToPoint,
+    {
+        self.tx.send(Message::Point(point.to_point())).await
+            .map_err(|_| InfluxDBError::WriteApiClosed)
+    }
+
+    /// Queues several points to be written.
+    pub async fn write_points<I, T>(&self, points: I) -> Result<(), InfluxDBError>
+    where
+        T: ToPoint,
+        I: IntoIterator<Item = T>,
+    {
+        for point in points {
+            self.write(point).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes everything queued so far and waits for the result.
+    pub async fn flush(&self) -> Result<(), InfluxDBError> {
+        request(self.tx.clone(), Message::Flush).await
+    }
+
+    /// Writes everything queued so far and stops the background task. Other
+    /// clones of this handle fail with [`InfluxDBError::WriteApiClosed`]
+    /// afterwards.
+    pub async fn close(self) -> Result<(), InfluxDBError> {
+        request(self.tx.clone(), Message::Close).await
+    }
+}
+
+async fn request(tx: mpsc::Sender<Message>, message: fn(oneshot::Sender<Result<(), InfluxDBError>>) -> Message) -> Result<(), InfluxDBError> {
+    let (reply, rx) = oneshot::channel();
+    tx.send(message(reply)).await
+        .map_err(|_| InfluxDBError::WriteApiClosed)?;
+    rx.await.map_err(|_| InfluxDBError::WriteApiClosed)?
+}
+
+impl<T> Sink<T> for WriteApi
+where
+    T: ToPoint,
+{
+    type Error = InfluxDBError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.sink.poll_reserve(cx).map_err(|_| InfluxDBError::WriteApiClosed)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.sink.send_item(Message::Point(item.to_point()))
+            .map_err(|_| InfluxDBError::WriteApiClosed)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Points from `start_send` are already in the channel, so a flush
+        // queued behind them covers them.
+        if self.flushing.is_none() {
+            std::task::ready!(self.sink.poll_reserve(cx)).map_err(|_| InfluxDBError::WriteApiClosed)?;
+            let (reply, rx) = oneshot::channel();
+            self.sink.send_item(Message::Flush(reply)).map_err(|_| InfluxDBError::WriteApiClosed)?;
+            self.flushing = Some(rx);
+        }
+        let flushing = self.flushing.as_mut().expect("flush was requested");
+        let result = std::task::ready!(flushing.poll_unpin(cx));
+        self.flushing = None;
+        Poll::Ready(result.map_err(|_| InfluxDBError::WriteApiClosed)?)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let result = std::task::ready!(<Self as Sink<T>>::poll_flush(self.as_mut(), cx));
+        self.sink.close();
+        Poll::Ready(result)
+    }
+}
+
+/// Point indexes in errors count every point accepted by the `WriteApi`
+/// since it was created.
+struct Buffer {
+    client: Client,
+    options: WriteApiOptions,
+    batcher: Batcher,
+    last_error: Option<InfluxDBError>,
+}
+
+impl Buffer {
+    fn add(&mut self, point: Point) -> Result<(), InfluxDBError> {
+        let config = self.client.write_config(&self.options.write_options);
+        self.batcher.add_point(point, &config)
+    }
+
+    fn is_full(&self) -> bool {
+        let (lines, bytes) = self.batcher.pending();
+        self.batcher.has_ready() || lines >= self.options.max_lines || bytes >= self.options.max_bytes
+    }
+
+    /// Sends everything buffered. A batch that fails is dropped and the ones
+    /// after it are still sent, so points go out in order; the errors of the
+    /// failed batches are returned in order.
+    async fn flush(&mut self) -> Vec<InfluxDBError> {
+        let config = self.client.write_config(&self.options.write_options);
+        let mut errors = Vec::new();
+        while let Some(batch) = self.batcher.take_any() {
+            if let Err(e) = self.client.send_batch(batch, &config).await {
+                errors.push(e);
+            }
+        }
+        errors
+    }
+
+    /// Flushes for nobody in particular, reporting every error.
+    async fn flush_unattended(&mut self) {
+        for err in self.flush().await {
+            self.report(err);
+        }
+    }
+
+    /// Routes an error nobody is waiting on to the error handler, or keeps it
+    /// for the next explicit flush.
+    fn report(&mut self, err: InfluxDBError) {
+        match &self.options.error_handler {
+            Some(handler) => handler(err),
+            None => self.last_error = Some(err),
+        }
+    }
+
+    /// Flushes for a caller waiting on the result. The first error goes to
+    /// the caller and the rest are reported.
+    async fn flush_requested(&mut self) -> Result<(), InfluxDBError> {
+        let mut errors = self.flush().await.into_iter();
+        let first = errors.next();
+        for err in errors {
+            self.report(err);
+        }
+        match first.or_else(|| self.last_error.take()) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+async fn run(client: Client, options: WriteApiOptions, mut rx: mpsc::Receiver<Message>) {
+    let mut interval = tokio::time::interval(options.flush_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval.reset();
+
+    let mut buffer = Buffer {
+        client,
+        options,
+        batcher: Batcher::new(),
+        last_error: None,
+    };
+
+    loop {
+        tokio::select! {
+            message = rx.recv() => match message {
+                Some(Message::Point(point)) => {
+                    if let Err(e) = buffer.add(point) {
+                        buffer.report(e);
+                    } else if buffer.is_full() {
+                        buffer.flush_unattended().await;
+                        interval.reset();
+                    }
+                },
+                Some(Message::Flush(reply)) => {
+                    let _ = reply.send(buffer.flush_requested().await);
+                    interval.reset();
+                },
+                Some(Message::Close(reply)) => {
+                    rx.close();
+                    // Points queued before the close are still part of this
+                    // write, and flushes and closes queued with them get its
+                    // result.
+                    let mut queued = Vec::new();
+                    while let Ok(message) = rx.try_recv() {
+                        match message {
+                            Message::Point(point) => {
+                                if let Err(e) = buffer.add(point) {
+                                    buffer.report(e);
+                                }
+                            },
+                            Message::Flush(reply) | Message::Close(reply) => queued.push(reply),
+                        }
+                    }
+                    let result = buffer.flush_requested().await;
+                    for queued_reply in queued {
+                        // Errors can't be cloned, so the others get its message.
+                        let copy = match &result {
+                            Ok(()) => Ok(()),
+                            Err(err) => Err(InfluxDBError::Other(err.to_string())),
+                        };
+                        let _ = queued_reply.send(copy);
+                    }
+                    let _ = reply.send(result);
+                    return;
+                },
+                None => {
+                    buffer.flush_unattended().await;
+                    return;
+                },
+            },
+            _ = interval.tick() => {
+                buffer.flush_unattended().await;
+                match buffer.client.drain_spool().await {
+                    Ok(report) => {
+                        for err in report.dropped {
+                            buffer.report(err);
+                        }
+                    },
+                    // Spooled batches are safe on disk, so failing to deliver
+                    // them yet is not worth reporting.
+                    Err(e) if buffer.client.retry_policy.is_retryable(&e) => {},
+                    Err(e) => buffer.report(e),
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! In-process stand-ins for the server's write and query endpoints.
+#![allow(dead_code)]
+
+pub mod query_server;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::{Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use influxdb3_core::{ClientBuilder, RetryPolicy};
+
+/// A request received by [`WriteServer`].
+#[derive(Debug, Clone)]
+pub struct WriteRequest {
+    pub params: HashMap<String, String>,
+    pub body: String,
+}
+
+#[derive(Default)]
+struct ServerState {
+    requests: Vec<WriteRequest>,
+    /// Status and JSON body of the next responses. Requests are answered with
+    /// 204 once this runs out.
+    responses: VecDeque<(u16, String)>,
+}
+
+#[derive(Clone)]
+pub struct WriteServer {
+    pub url: String,
+    state: Arc<Mutex<ServerState>>,
+}
+
+impl WriteServer {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(ServerState::default()));
+        let app = Router::new()
+            .route("/api/v3/write_lp", post(write))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Self { url, state }
+    }
+
+    /// Answers the next request not yet answered by an earlier call with
+    /// `status` and the JSON `body`.
+    pub fn respond(&self, status: u16, body: &str) {
+        self.state.lock().unwrap().responses.push_back((status, body.to_string()));
+    }
+
+    pub fn requests(&self) -> Vec<WriteRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn bodies(&self) -> Vec<String> {
+        self.requests().into_iter().map(|r| r.body).collect()
+    }
+
+    /// Waits up to five seconds for the server to have received `n`
+    /// requests, successful or not.
+    pub async fn wait_for_requests(&self, n: usize) -> Vec<WriteRequest> {
+        for _ in 0..500 {
+            let requests = self.requests();
+            if requests.len() >= n {
+                return requests;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("expected {n} requests, got {:?}", self.requests());
+    }
+
+    /// A client writing to this server, without compression or retries.
+    pub fn client(&self) -> ClientBuilder {
+        ClientBuilder::new()
+            .host(&self.url).unwrap()
+            .token("token")
+            .database("db")
+            .gzip_threshold(0)
+            .retry_policy(RetryPolicy::none())
+    }
+}
+
+async fn write(State(state): State<Arc<Mutex<ServerState>>>, Query(params): Query<HashMap<String, String>>, body: String) -> Response {
+    let mut state = state.lock().unwrap();
+    state.requests.push(WriteRequest { params, body });
+    match state.responses.pop_front() {
+        Some((status, body)) => (
+            StatusCode::from_u16(status).unwrap(),
+            [(header::CONTENT_TYPE, "application/json")],
+            body,
+        ).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
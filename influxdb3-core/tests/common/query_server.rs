@@ -0,0 +1,121 @@
+//! An in-process stand-in for the server's Flight query endpoint.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use arrow_array::RecordBatch;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightInfo, HandshakeRequest, HandshakeResponse, PollInfo,
+    PutResult, SchemaResult, Ticket,
+};
+use futures::stream::{self, BoxStream, StreamExt as _, TryStreamExt as _};
+use influxdb3_core::{Client, RetryPolicy};
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status, Streaming};
+
+#[derive(Default)]
+struct ServerState {
+    /// The JSON tickets received, one per query.
+    tickets: Vec<serde_json::Value>,
+    /// Results of the next queries. Queries are refused once this runs out.
+    responses: VecDeque<RecordBatch>,
+}
+
+#[derive(Clone, Default)]
+pub struct QueryServer {
+    state: Arc<Mutex<ServerState>>,
+}
+
+impl QueryServer {
+    /// Starts the server and returns a client querying it.
+    pub async fn start() -> (Self, Client) {
+        let server = Self::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let serve = Server::builder()
+            .add_service(FlightServiceServer::new(server.clone()))
+            .serve_with_incoming(TcpIncoming::from(listener));
+        tokio::spawn(async move { serve.await.unwrap() });
+        let client = Client::builder()
+            .host(&url).unwrap()
+            .token("token")
+            .database("db")
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        (server, client)
+    }
+
+    /// Answers the next query not yet answered by an earlier call with
+    /// `batch`.
+    pub fn respond(&self, batch: RecordBatch) {
+        self.state.lock().unwrap().responses.push_back(batch);
+    }
+
+    pub fn tickets(&self) -> Vec<serde_json::Value> {
+        self.state.lock().unwrap().tickets.clone()
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for QueryServer {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoActionStream = BoxStream<'static, Result<arrow_flight::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+
+    async fn handshake(&self, _request: Request<Streaming<HandshakeRequest>>) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("handshake"))
+    }
+
+    async fn list_flights(&self, _request: Request<Criteria>) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("list_flights"))
+    }
+
+    async fn get_flight_info(&self, _request: Request<FlightDescriptor>) -> Result<Response<FlightInfo>, Status> {
+        Err(Status::unimplemented("get_flight_info"))
+    }
+
+    async fn poll_flight_info(&self, _request: Request<FlightDescriptor>) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("poll_flight_info"))
+    }
+
+    async fn get_schema(&self, _request: Request<FlightDescriptor>) -> Result<Response<SchemaResult>, Status> {
+        Err(Status::unimplemented("get_schema"))
+    }
+
+    async fn do_get(&self, request: Request<Ticket>) -> Result<Response<Self::DoGetStream>, Status> {
+        let ticket = serde_json::from_slice(&request.get_ref().ticket)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let mut state = self.state.lock().unwrap();
+        state.tickets.push(ticket);
+        let batch = state.responses.pop_front()
+            .ok_or_else(|| Status::unavailable("no response queued"))?;
+        let data = FlightDataEncoderBuilder::new()
+            .build(stream::iter([Ok(batch)]))
+            .map_err(Status::from);
+        Ok(Response::new(data.boxed()))
+    }
+
+    async fn do_put(&self, _request: Request<Streaming<FlightData>>) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("do_put"))
+    }
+
+    async fn do_action(&self, _request: Request<Action>) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("do_action"))
+    }
+
+    async fn list_actions(&self, _request: Request<Empty>) -> Result<Response<Self::ListActionsStream>, Status> {
+        Err(Status::unimplemented("list_actions"))
+    }
+
+    async fn do_exchange(&self, _request: Request<Streaming<FlightData>>) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("do_exchange"))
+    }
+}
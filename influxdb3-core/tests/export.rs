@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray, TimestampNanosecondArray};
+use arrow_flight::decode::FlightDataDecoder;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use futures::StreamExt as _;
+use influxdb3_core::{export, Csv, ExportFormat, JsonLines, PointStream, RecordBatchStream};
+
+fn column_type(field: Field, column_type: &str) -> Field {
+    field.with_metadata([("iox::column::type".to_string(), column_type.to_string())].into())
+}
+
+/// A `cpu` table as the server returns it: a tag, a field, the time and a
+/// computed column.
+fn cpu() -> RecordBatch {
+    let schema = Schema::new(vec![
+        column_type(Field::new("host", DataType::Utf8, true), "iox::column_type::tag"),
+        column_type(Field::new("usage", DataType::Float64, true), "iox::column_type::field::float"),
+        column_type(Field::new("time", DataType::Timestamp(TimeUnit::Nanosecond, None), false), "iox::column_type::timestamp"),
+        Field::new("n", DataType::Int64, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(vec![Some("a,b"), None])),
+        Arc::new(Float64Array::from(vec![Some(1.5), None])),
+        Arc::new(TimestampNanosecondArray::from(vec![1_700_000_000_123_456_789, 1_700_000_000_000_000_000])),
+        Arc::new(Int64Array::from(vec![Some(3), Some(-1)])),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns).unwrap()
+}
+
+async fn record_batches(batches: Vec<RecordBatch>) -> RecordBatchStream {
+    let flight_data = FlightDataEncoderBuilder::new().build(futures::stream::iter(batches.into_iter().map(Ok)));
+    RecordBatchStream::new(FlightDataDecoder::new(flight_data)).await.unwrap()
+}
+
+async fn exported<F: ExportFormat>(format: F) -> String {
+    let mut out = Vec::new();
+    record_batches(vec![cpu()]).await.export(format, &mut out).await.unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[tokio::test]
+async fn csv() {
+    assert_eq!(exported(Csv::new()).await, concat!(
+        "host,usage,time,n\n",
+        "\"a,b\",1.5,2023-11-14T22:13:20.123456789Z,3\n",
+        ",,2023-11-14T22:13:20Z,-1\n",
+    ));
+}
+
+#[tokio::test]
+async fn annotated_csv() {
+    assert_eq!(exported(Csv::new().annotated(true)).await, concat!(
+        "#datatype,tag,double,dateTime:RFC3339,long\n",
+        "#group,true,false,false,false\n",
+        "#default,,,,\n",
+        ",host,usage,time,n\n",
+        ",\"a,b\",1.5,2023-11-14T22:13:20.123456789Z,3\n",
+        ",,,2023-11-14T22:13:20Z,-1\n",
+    ));
+}
+
+#[tokio::test]
+async fn annotated_csv_keeps_the_measurement() {
+    let schema = Schema::new(vec![
+        Field::new("iox::measurement", DataType::Utf8, false),
+        column_type(Field::new("count", DataType::Int64, true), "iox::column_type::field::integer"),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(vec!["cpu"])),
+        Arc::new(Int64Array::from(vec![7])),
+    ];
+    let batch = RecordBatch::try_new(Arc::new(schema), columns).unwrap();
+
+    let mut out = Vec::new();
+    record_batches(vec![batch]).await.export(Csv::new().annotated(true), &mut out).await.unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), concat!(
+        "#datatype,measurement,long\n",
+        "#group,true,false\n",
+        "#default,,\n",
+        ",iox::measurement,count\n",
+        ",cpu,7\n",
+    ));
+}
+
+#[tokio::test]
+async fn json_lines_group_columns_by_role() {
+    assert_eq!(exported(JsonLines::new()).await, concat!(
+        r#"{"fields":{"n":3,"usage":1.5},"tags":{"host":"a,b"},"time":"2023-11-14T22:13:20.123456789Z"}"#, "\n",
+        r#"{"fields":{"n":-1},"tags":{},"time":"2023-11-14T22:13:20Z"}"#, "\n",
+    ));
+}
+
+#[cfg(feature = "table")]
+#[tokio::test]
+async fn table() {
+    use influxdb3_core::Table;
+
+    assert_eq!(exported(Table::new()).await, concat!(
+        "+------+-------+--------------------------------+----+\n",
+        "| host | usage | time                           |  n |\n",
+        "| tag  | field | time                           |    |\n",
+        "+====================================================+\n",
+        "| a,b  |   1.5 | 2023-11-14T22:13:20.123456789Z |  3 |\n",
+        "|------+-------+--------------------------------+----|\n",
+        "|      |       | 2023-11-14T22:13:20Z           | -1 |\n",
+        "+------+-------+--------------------------------+----+\n",
+    ));
+}
+
+#[tokio::test]
+async fn empty_results_still_have_a_header() {
+    let mut out = Vec::new();
+    let schema = cpu().schema();
+    export(schema, futures::stream::empty(), Csv::new(), &mut out).await.unwrap();
+    assert_eq!(out, b"host,usage,time,n\n");
+}
+
+#[tokio::test]
+async fn point_streams_export_the_rows_not_read() {
+    let mut points = PointStream::new(record_batches(vec![cpu(), cpu()]).await);
+    points.next().await.unwrap().unwrap();
+
+    let mut out = Vec::new();
+    points.export(Csv::new(), &mut out).await.unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out.lines().count(), 1 + 3);
+    assert!(out.lines().nth(1).unwrap().ends_with(",-1"));
+}
+
+#[cfg(feature = "parquet")]
+#[tokio::test]
+async fn parquet_keeps_column_roles() {
+    use arrow_array::RecordBatchReader as _;
+    use influxdb3_core::{ColumnRole, Parquet, ResultSchema};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let mut out = Vec::new();
+    record_batches(vec![cpu(), cpu()]).await.export(Parquet::new(), &mut out).await.unwrap();
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(out)).unwrap().build().unwrap();
+    let schema = ResultSchema::new(reader.schema());
+    assert_eq!(schema.column("host").unwrap().role, ColumnRole::Tag);
+    assert_eq!(schema.time_column().unwrap().name, "time");
+    let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+    assert_eq!(rows, 4);
+}
@@ -0,0 +1,188 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use arrow_array::cast::AsArray as _;
+use arrow_array::types::Int64Type;
+use arrow_array::RecordBatch;
+use arrow_flight::decode::FlightRecordBatchStream;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::sql::server::{FlightSqlService, PeekableFlightDataStream};
+use arrow_flight::sql::{
+    ActionClosePreparedStatementRequest, ActionCreatePreparedStatementRequest, ActionCreatePreparedStatementResult,
+    CommandPreparedStatementQuery, DoPutPreparedStatementResult, ProstMessageExt as _, SqlInfo,
+};
+use arrow_flight::{Action, FlightEndpoint, FlightInfo, IpcMessage, SchemaAsIpc, Ticket};
+use arrow_schema::{DataType, Field, Schema};
+use bytes::Bytes;
+use futures::{stream, TryStreamExt as _};
+use influxdb3_core::{Client, InfluxDBError, PointValue};
+use prost::Message as _;
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+const HANDLE: &[u8] = b"statement-1";
+
+#[derive(Default)]
+struct ServerState {
+    queries: Vec<String>,
+    /// The parameters bound last, which executing the statement returns.
+    params: Option<RecordBatch>,
+    closed: Vec<Bytes>,
+}
+
+/// An in-process Flight SQL server that plans every query the same way and
+/// answers it with the parameters bound to it.
+#[derive(Clone, Default)]
+struct PreparedStatementServer {
+    state: Arc<Mutex<ServerState>>,
+    /// Never answers a request to prepare a statement.
+    stall_prepare: bool,
+    /// Never answers a request to close a statement.
+    stall_close: bool,
+}
+
+impl PreparedStatementServer {
+    /// Starts the server and returns a client connected to it.
+    async fn start(&self, timeout: Duration) -> Client {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = Server::builder()
+            .add_service(FlightServiceServer::new(self.clone()))
+            .serve_with_incoming(TcpIncoming::from(listener));
+        tokio::spawn(async move { server.await.unwrap() });
+        Client::builder()
+            .host(&url).unwrap()
+            .token("token")
+            .database("db")
+            .timeout(timeout)
+            .build()
+            .unwrap()
+    }
+}
+
+fn parameter_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("a", DataType::Int64, true),
+        Field::new("b", DataType::Utf8, true),
+    ])
+}
+
+fn ipc(schema: &Schema) -> Bytes {
+    IpcMessage::try_from(SchemaAsIpc::new(schema, &Default::default())).unwrap().0
+}
+
+#[tonic::async_trait]
+impl FlightSqlService for PreparedStatementServer {
+    type FlightService = Self;
+
+    async fn do_action_create_prepared_statement(
+        &self,
+        query: ActionCreatePreparedStatementRequest,
+        _request: Request<Action>,
+    ) -> Result<ActionCreatePreparedStatementResult, Status> {
+        if self.stall_prepare {
+            std::future::pending::<()>().await;
+        }
+        self.state.lock().unwrap().queries.push(query.query);
+        Ok(ActionCreatePreparedStatementResult {
+            prepared_statement_handle: Bytes::from_static(HANDLE),
+            dataset_schema: ipc(&parameter_schema()),
+            parameter_schema: ipc(&parameter_schema()),
+        })
+    }
+
+    async fn do_put_prepared_statement_query(
+        &self,
+        _query: CommandPreparedStatementQuery,
+        request: Request<PeekableFlightDataStream>,
+    ) -> Result<DoPutPreparedStatementResult, Status> {
+        let data = request.into_inner().map_err(FlightError::from);
+        let batches: Vec<_> = FlightRecordBatchStream::new_from_flight_data(data).try_collect().await?;
+        self.state.lock().unwrap().params = batches.into_iter().next();
+        Ok(DoPutPreparedStatementResult { prepared_statement_handle: None })
+    }
+
+    async fn get_flight_info_prepared_statement(
+        &self,
+        query: CommandPreparedStatementQuery,
+        _request: Request<arrow_flight::FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let ticket = Ticket::new(query.as_any().encode_to_vec());
+        Ok(Response::new(FlightInfo::new().with_endpoint(FlightEndpoint::new().with_ticket(ticket))))
+    }
+
+    async fn do_get_prepared_statement(
+        &self,
+        _query: CommandPreparedStatementQuery,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let params = self.state.lock().unwrap().params.clone()
+            .ok_or_else(|| Status::failed_precondition("no parameters bound"))?;
+        let data = FlightDataEncoderBuilder::new()
+            .build(stream::iter([Ok(params)]))
+            .map_err(Status::from);
+        Ok(Response::new(Box::pin(data)))
+    }
+
+    async fn do_action_close_prepared_statement(
+        &self,
+        query: ActionClosePreparedStatementRequest,
+        _request: Request<Action>,
+    ) -> Result<(), Status> {
+        if self.stall_close {
+            std::future::pending::<()>().await;
+        }
+        self.state.lock().unwrap().closed.push(query.prepared_statement_handle);
+        Ok(())
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+#[tokio::test]
+async fn prepared_statement_round_trip() {
+    let server = PreparedStatementServer::default();
+    let client = server.start(Duration::from_secs(5)).await;
+    let mut flight_sql = client.flight_sql();
+
+    let mut statement = flight_sql.prepare("SELECT $a, $b").await.unwrap();
+    assert_eq!(*statement.parameter_schema(), parameter_schema());
+    assert_eq!(*statement.dataset_schema(), parameter_schema());
+
+    for (a, b) in [(1, "x"), (2, "y")] {
+        statement.bind([("a", PointValue::Integer(a)), ("b", PointValue::String(b.to_string()))]).unwrap();
+        let batches: Vec<_> = statement.execute().await.unwrap().try_collect().await.unwrap();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.column_by_name("a").unwrap().as_primitive::<Int64Type>().value(0), a);
+        assert_eq!(batch.column_by_name("b").unwrap().as_string::<i32>().value(0), b);
+    }
+
+    statement.close().await.unwrap();
+    let state = server.state.lock().unwrap();
+    assert_eq!(state.queries, ["SELECT $a, $b"]);
+    assert_eq!(state.closed, [Bytes::from_static(HANDLE)]);
+}
+
+#[tokio::test]
+async fn prepare_times_out() {
+    let server = PreparedStatementServer { stall_prepare: true, ..Default::default() };
+    let client = server.start(Duration::from_millis(100)).await;
+
+    let err = client.flight_sql().prepare("SELECT 1").await.unwrap_err();
+    assert!(matches!(err, InfluxDBError::QueryTimeout), "{err:?}");
+}
+
+#[tokio::test]
+async fn close_times_out() {
+    let server = PreparedStatementServer { stall_close: true, ..Default::default() };
+    let client = server.start(Duration::from_millis(100)).await;
+
+    let statement = client.flight_sql().prepare("SELECT 1").await.unwrap();
+    let err = statement.close().await.unwrap_err();
+    assert!(matches!(err, InfluxDBError::QueryTimeout), "{err:?}");
+    assert!(server.state.lock().unwrap().closed.is_empty());
+}
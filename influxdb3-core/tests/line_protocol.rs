@@ -0,0 +1,236 @@
+use chrono::{DateTime, TimeZone as _, Utc};
+use influxdb3_core::{InfluxDBError, NonFiniteFloatPolicy, Point, PointValue, TagMap, TagName, TimestampPrecision};
+
+fn time() -> DateTime<Utc> {
+    Utc.timestamp_opt(1_700_000_000, 123_456_789).unwrap()
+}
+
+fn point(measurement: &str) -> Point {
+    let mut point = Point::new_with_measurement(measurement);
+    point.set_timestamp(time());
+    point
+}
+
+fn line(point: &Point) -> String {
+    point.to_line_protocol(TimestampPrecision::Nanoseconds).unwrap()
+}
+
+fn serialize(point: &Point, default_tags: &TagMap, float_policy: NonFiniteFloatPolicy) -> Result<String, InfluxDBError> {
+    let mut buf = Vec::new();
+    point.serialize(&mut buf, TimestampPrecision::Nanoseconds, default_tags, float_policy)?;
+    Ok(String::from_utf8(buf).unwrap())
+}
+
+#[test]
+fn field_types() {
+    let cases: [(PointValue, &str); 8] = [
+        (PointValue::Float(1.5), "1.5"),
+        (PointValue::Float(-3.0), "-3"),
+        (PointValue::Integer(-42), "-42i"),
+        (PointValue::UInteger(u64::MAX), "18446744073709551615u"),
+        (PointValue::Boolean(true), "t"),
+        (PointValue::Boolean(false), "f"),
+        (PointValue::String("hello".to_string()), "\"hello\""),
+        (PointValue::Timestamp(time()), "1700000000123456789i"),
+    ];
+    for (value, expected) in cases {
+        let mut p = point("m");
+        p.set_field("v", value);
+        assert_eq!(line(&p), format!("m v={expected} 1700000000123456789"));
+    }
+}
+
+#[test]
+fn floats_round_trip() {
+    for value in [0.1 + 0.2, 1e21, -1e-7, f64::MAX, f64::MIN_POSITIVE, 123456789.0] {
+        let mut p = point("m");
+        p.set_field("v", value);
+        let line = line(&p);
+        let written = line.strip_prefix("m v=").unwrap().strip_suffix(" 1700000000123456789").unwrap();
+        assert_eq!(written.parse::<f64>().unwrap(), value, "{line}");
+    }
+}
+
+#[test]
+fn decimals_serialize_as_floats() {
+    let cases = [((12345, 2), "123.45"), ((-5, 3), "-0.005"), ((7, -2), "700"), ((0, -3), "0"), ((-12, 0), "-12")];
+    for ((value, scale), expected) in cases {
+        let mut p = point("m");
+        p.set_field("v", PointValue::Decimal(value, scale));
+        assert_eq!(line(&p), format!("m v={expected} 1700000000123456789"));
+    }
+}
+
+#[test]
+fn measurement_escaping() {
+    let mut p = point("my measurement,with=specials\\");
+    p.set_field("v", 1i64);
+    assert_eq!(line(&p), "my\\ measurement\\,with=specials\\\\ v=1i 1700000000123456789");
+}
+
+#[test]
+fn tag_value_escaping() {
+    let mut p = point("m");
+    p.set_tag("host", "a b,c=d\\e");
+    p.set_field("v", 1i64);
+    assert_eq!(line(&p), "m,host=a\\ b\\,c\\=d\\\\e v=1i 1700000000123456789");
+}
+
+#[test]
+fn field_key_escaping() {
+    let mut p = point("m");
+    p.fields.insert("a b,c=d".to_string(), PointValue::Integer(1));
+    assert_eq!(line(&p), "m a\\ b\\,c\\=d=1i 1700000000123456789");
+}
+
+#[test]
+fn string_field_escaping() {
+    let mut p = point("m");
+    p.set_field("v", "say \"hi\", path=C:\\tmp\nnext line");
+    assert_eq!(line(&p), "m v=\"say \\\"hi\\\", path=C:\\\\tmp\nnext line\" 1700000000123456789");
+}
+
+#[test]
+fn null_fields_are_skipped() {
+    let mut p = point("m");
+    p.set_field("missing", None::<i64>);
+    p.set_field("v", 1i64);
+    assert_eq!(line(&p), "m v=1i 1700000000123456789");
+}
+
+#[test]
+fn point_without_fields_is_rejected() {
+    let p = point("m");
+    assert!(matches!(p.to_line_protocol(TimestampPrecision::Nanoseconds), Err(InfluxDBError::InvalidPoint(_))));
+
+    let mut p = point("m");
+    p.set_field("missing", None::<i64>);
+    assert!(matches!(p.to_line_protocol(TimestampPrecision::Nanoseconds), Err(InfluxDBError::InvalidPoint(_))));
+}
+
+#[test]
+fn empty_measurement_is_rejected() {
+    let mut p = point("");
+    p.set_field("v", 1i64);
+    assert!(matches!(p.to_line_protocol(TimestampPrecision::Nanoseconds), Err(InfluxDBError::InvalidPoint(_))));
+}
+
+#[test]
+fn measurement_read_as_blank_or_comment_is_rejected() {
+    for name in ["#m", " m", "\tm", "\u{c}m"] {
+        let mut p = point(name);
+        p.set_field("v", 1i64);
+        assert!(matches!(p.to_line_protocol(TimestampPrecision::Nanoseconds), Err(InfluxDBError::InvalidPoint(_))), "{name:?}");
+    }
+
+    let mut p = point("m#");
+    p.set_field("v", 1i64);
+    assert_eq!(line(&p), "m# v=1i 1700000000123456789");
+}
+
+#[test]
+fn newlines_outside_strings_are_rejected() {
+    let mut p = point("m");
+    p.set_tag("host", "a\nb");
+    p.set_field("v", 1i64);
+    assert!(matches!(p.to_line_protocol(TimestampPrecision::Nanoseconds), Err(InfluxDBError::InvalidPoint(_))));
+
+    let mut p = point("m\r");
+    p.set_field("v", 1i64);
+    assert!(matches!(p.to_line_protocol(TimestampPrecision::Nanoseconds), Err(InfluxDBError::InvalidPoint(_))));
+}
+
+#[test]
+fn empty_tag_values_are_skipped() {
+    let mut p = point("m");
+    p.set_tag("host", "");
+    p.set_field("v", 1i64);
+    assert_eq!(line(&p), "m v=1i 1700000000123456789");
+}
+
+#[test]
+fn non_finite_floats_rejected_by_default() {
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let mut p = point("m");
+        p.set_field("v", value);
+        assert!(matches!(serialize(&p, &TagMap::new(), NonFiniteFloatPolicy::Reject), Err(InfluxDBError::InvalidPoint(_))));
+    }
+}
+
+#[test]
+fn non_finite_floats_skipped() {
+    let mut p = point("m");
+    p.set_field("bad", f64::NAN);
+    p.set_field("good", 2.5);
+    assert_eq!(serialize(&p, &TagMap::new(), NonFiniteFloatPolicy::Skip).unwrap(), "m good=2.5 1700000000123456789\n");
+
+    let mut p = point("m");
+    p.set_field("bad", f64::INFINITY);
+    assert!(matches!(serialize(&p, &TagMap::new(), NonFiniteFloatPolicy::Skip), Err(InfluxDBError::InvalidPoint(_))));
+}
+
+#[test]
+fn default_tags_are_escaped() {
+    let mut default_tags = TagMap::new();
+    default_tags.insert(TagName::try_from("region").unwrap(), "us west".to_string());
+    let mut p = point("m");
+    p.set_field("v", 1i64);
+    assert_eq!(serialize(&p, &default_tags, NonFiniteFloatPolicy::Reject).unwrap(), "m,region=us\\ west v=1i 1700000000123456789\n");
+}
+
+#[test]
+fn tags_and_fields_are_sorted() {
+    let mut p = point("m");
+    p.set_tag("zone", "z").set_tag("app", "a").set_tag("host", "h");
+    p.set_field("z", 1i64).set_field("a", 2i64).set_field("m", 3i64);
+    assert_eq!(line(&p), "m,app=a,host=h,zone=z a=2i,m=3i,z=1i 1700000000123456789");
+}
+
+#[test]
+fn point_tags_override_default_tags() {
+    let mut default_tags = TagMap::new();
+    for (key, value) in [("app", "default"), ("host", "default"), ("zone", "default"), ("dc", "default")] {
+        default_tags.insert(TagName::try_from(key).unwrap(), value.to_string());
+    }
+    let mut p = point("m");
+    p.set_tag("host", "h").set_tag("region", "r").set_tag("dc", "");
+    p.set_field("v", 1i64);
+    assert_eq!(
+        serialize(&p, &default_tags, NonFiniteFloatPolicy::Reject).unwrap(),
+        "m,app=default,host=h,region=r,zone=default v=1i 1700000000123456789\n",
+    );
+}
+
+#[test]
+fn failed_serialization_leaves_buffer_untouched() {
+    let mut buf = b"existing\n".to_vec();
+    let mut p = point("m");
+    p.set_tag("host", "ok");
+    p.set_field("v", f64::NAN);
+    assert!(p.serialize(&mut buf, TimestampPrecision::Nanoseconds, &TagMap::new(), NonFiniteFloatPolicy::Reject).is_err());
+    assert_eq!(buf, b"existing\n");
+}
+
+#[test]
+fn timestamp_precision() {
+    let mut p = point("m");
+    p.set_field("v", 1i64);
+    let cases = [
+        (TimestampPrecision::Nanoseconds, "1700000000123456789"),
+        (TimestampPrecision::Microseconds, "1700000000123456"),
+        (TimestampPrecision::Milliseconds, "1700000000123"),
+        (TimestampPrecision::Seconds, "1700000000"),
+    ];
+    for (precision, expected) in cases {
+        assert_eq!(p.to_line_protocol(precision).unwrap(), format!("m v=1i {expected}"));
+    }
+}
+
+#[test]
+fn timestamp_out_of_range_for_nanoseconds() {
+    let mut p = point("m");
+    p.set_timestamp(Utc.with_ymd_and_hms(2300, 1, 1, 0, 0, 0).unwrap());
+    p.set_field("v", 1i64);
+    assert!(matches!(p.to_line_protocol(TimestampPrecision::Nanoseconds), Err(InfluxDBError::InvalidPoint(_))));
+    assert!(p.to_line_protocol(TimestampPrecision::Seconds).is_ok());
+}
@@ -0,0 +1,217 @@
+use std::io::Cursor;
+
+use chrono::{DateTime, TimeZone as _, Utc};
+use futures::StreamExt as _;
+use influxdb3_core::{InfluxDBError, LineProtocolReader, ParseError, Point, PointValue, TagName, TimestampPrecision};
+use proptest::prelude::*;
+
+const NS: TimestampPrecision = TimestampPrecision::Nanoseconds;
+
+fn time() -> DateTime<Utc> {
+    Utc.timestamp_opt(1_700_000_000, 123_456_789).unwrap()
+}
+
+fn tag(name: &str) -> TagName {
+    TagName::try_from(name).unwrap()
+}
+
+fn parse(line: &str) -> Point {
+    Point::from_line_protocol(line, NS).unwrap()
+}
+
+fn parse_err(line: &str) -> ParseError {
+    match Point::from_line_protocol(line, NS) {
+        Err(InfluxDBError::Parse(e)) => e,
+        other => panic!("expected a parse error for {line:?}, got {other:?}"),
+    }
+}
+
+#[test]
+fn field_types() {
+    let p = parse("m f=1.5,g=-3,e=1e21,i=-42i,u=18446744073709551615u,t=t,tt=TRUE,f2=false,s=\"hi\" 1700000000123456789");
+    assert_eq!(p.fields["f"], PointValue::Float(1.5));
+    assert_eq!(p.fields["g"], PointValue::Float(-3.0));
+    assert_eq!(p.fields["e"], PointValue::Float(1e21));
+    assert_eq!(p.fields["i"], PointValue::Integer(-42));
+    assert_eq!(p.fields["u"], PointValue::UInteger(u64::MAX));
+    assert_eq!(p.fields["t"], PointValue::Boolean(true));
+    assert_eq!(p.fields["tt"], PointValue::Boolean(true));
+    assert_eq!(p.fields["f2"], PointValue::Boolean(false));
+    assert_eq!(p.fields["s"], PointValue::String("hi".to_string()));
+    assert_eq!(p.time, time());
+}
+
+#[test]
+fn escapes() {
+    let p = parse(r#"my\ m\,x,t\ k\=1=v\,a\=l\ ue f\,k\=1\ x="say \"hi\" \\ \n" 1"#);
+    assert_eq!(p.measurement_name, "my m,x");
+    assert_eq!(p.tags.iter().map(|(k, v)| (k.as_ref(), v.as_str())).collect::<Vec<_>>(), [("t k=1", "v,a=l ue")]);
+    assert_eq!(p.fields["f,k=1 x"], PointValue::String("say \"hi\" \\ \\n".to_string()));
+}
+
+#[test]
+fn backslash_before_other_characters_is_literal() {
+    let p = parse(r"m\x,t=a\b f=1i");
+    assert_eq!(p.measurement_name, r"m\x");
+    assert_eq!(p.tags[&tag("t")], r"a\b");
+}
+
+#[test]
+fn timestamp_precision() {
+    for (precision, ts) in [
+        (TimestampPrecision::Nanoseconds, "1700000000123456789"),
+        (TimestampPrecision::Microseconds, "1700000000123456"),
+        (TimestampPrecision::Milliseconds, "1700000000123"),
+        (TimestampPrecision::Seconds, "1700000000"),
+    ] {
+        let p = Point::from_line_protocol(&format!("m v=1i {ts}"), precision).unwrap();
+        assert_eq!(p.time.to_rfc3339(), precision_truncated(precision).to_rfc3339(), "{precision:?}");
+    }
+}
+
+fn precision_truncated(precision: TimestampPrecision) -> DateTime<Utc> {
+    let nanos = time().timestamp_nanos_opt().unwrap();
+    let unit = match precision {
+        TimestampPrecision::Nanoseconds => 1,
+        TimestampPrecision::Microseconds => 1_000,
+        TimestampPrecision::Milliseconds => 1_000_000,
+        TimestampPrecision::Seconds => 1_000_000_000,
+    };
+    DateTime::from_timestamp_nanos(nanos / unit * unit)
+}
+
+#[test]
+fn missing_timestamp_uses_current_time() {
+    let before = Utc::now();
+    let p = parse("m v=1i");
+    assert!(p.time >= before && p.time <= Utc::now());
+}
+
+#[test]
+fn trailing_newline_is_allowed() {
+    assert_eq!(parse("m v=1i 1\r\n").fields["v"], PointValue::Integer(1));
+}
+
+#[test]
+fn errors_report_line_and_column() {
+    let cases = [
+        ("m", 2, "line has no fields"),
+        (",t=1 v=1i", 1, "measurement name is empty"),
+        ("m,t= v=1i", 5, "tag t has an empty value"),
+        ("m,t v=1i", 4, "expected '=' after tag key"),
+        ("m v=1x", 5, "invalid field value \"1x\""),
+        ("m v=nan", 5, "invalid field value \"nan\""),
+        ("m v=1e999", 5, "float field value 1e999 is out of range"),
+        ("m v=99999999999999999999i", 5, "invalid field value \"99999999999999999999i\""),
+        ("m v=\"open", 5, "unterminated string field"),
+        ("m v=1i abc", 8, "invalid timestamp \"\""),
+        ("m v=1i 12 x", 11, "unexpected character 'x'"),
+    ];
+    for (line, column, message) in cases {
+        let e = parse_err(line);
+        assert_eq!((e.line, e.column, e.message.as_str()), (1, column, message), "{line:?}");
+    }
+}
+
+#[test]
+fn errors_inside_multiline_strings_point_at_the_physical_line() {
+    let e = parse_err("m s=\"a\nbc\",v=1x");
+    assert_eq!((e.line, e.column), (2, 7));
+}
+
+#[test]
+fn more_than_one_line_is_an_error() {
+    let e = parse_err("m v=1i\nm v=2i");
+    assert_eq!((e.line, e.column, e.message.as_str()), (2, 1, "expected a single line"));
+}
+
+const INPUT: &str = "# a comment\n\
+                     \n\
+                     cpu,host=a usage=1.5 1\n\
+                     log msg=\"two\n\
+                     lines\" 2\n\
+                     bad line\n\
+                     cpu,host=b usage=2.5 3";
+
+fn check_read(results: Vec<Result<Point, InfluxDBError>>) {
+    assert_eq!(results.len(), 4);
+    let first = results[0].as_ref().unwrap();
+    assert_eq!(first.tags[&tag("host")], "a");
+    assert_eq!(results[1].as_ref().unwrap().fields["msg"], PointValue::String("two\nlines".to_string()));
+    match &results[2] {
+        Err(InfluxDBError::Parse(e)) => assert_eq!((e.line, e.column), (6, 9)),
+        other => panic!("expected a parse error, got {other:?}"),
+    }
+    assert_eq!(results[3].as_ref().unwrap().time, DateTime::from_timestamp_nanos(3));
+}
+
+#[test]
+fn reader_sync() {
+    check_read(LineProtocolReader::new(Cursor::new(INPUT), NS).collect());
+}
+
+#[tokio::test]
+async fn reader_async() {
+    check_read(LineProtocolReader::new(INPUT.as_bytes(), NS).into_stream().collect().await);
+}
+
+fn name() -> impl Strategy<Value = String> {
+    "[^\r\n]{1,12}"
+}
+
+fn field_value() -> impl Strategy<Value = PointValue> {
+    prop_oneof![
+        any::<f64>().prop_filter("finite", |v| v.is_finite()).prop_map(PointValue::Float),
+        any::<i64>().prop_map(PointValue::Integer),
+        any::<u64>().prop_map(PointValue::UInteger),
+        any::<bool>().prop_map(PointValue::Boolean),
+        any::<String>().prop_map(PointValue::String),
+    ]
+}
+
+fn point() -> impl Strategy<Value = Point> {
+    (
+        name(),
+        prop::collection::btree_map("[a-zA-Z0-9][a-zA-Z0-9_-]{0,8}", name(), 0..4),
+        prop::collection::btree_map(name(), field_value(), 1..5),
+        any::<i64>(),
+    )
+        .prop_map(|(measurement, tags, fields, nanos)| Point {
+            measurement_name: measurement,
+            tags: tags.into_iter().map(|(k, v)| (tag(&k), v)).collect(),
+            fields: fields.into_iter().collect(),
+            time: DateTime::from_timestamp_nanos(nanos),
+        })
+}
+
+/// Measurements the server would read as a blank line or a comment, which
+/// serialization refuses.
+fn unwritable(point: &Point) -> bool {
+    point.measurement_name.starts_with(|c: char| c.is_ascii_whitespace() || c == '#')
+}
+
+proptest! {
+    #[test]
+    fn round_trip(point in point()) {
+        if unwritable(&point) {
+            prop_assert!(matches!(point.to_line_protocol(NS), Err(InfluxDBError::InvalidPoint(_))));
+            return Ok(());
+        }
+        let line = point.to_line_protocol(NS).unwrap();
+        let parsed = Point::from_line_protocol(&line, NS).unwrap();
+        prop_assert_eq!(&parsed, &point);
+        prop_assert_eq!(parsed.to_line_protocol(NS).unwrap(), line);
+    }
+
+    #[test]
+    fn round_trip_through_reader(points in prop::collection::vec(point(), 1..5)) {
+        let points: Vec<Point> = points.into_iter().filter(|point| !unwritable(point)).collect();
+        let mut input = String::new();
+        for point in &points {
+            input.push_str(&point.to_line_protocol(NS).unwrap());
+            input.push('\n');
+        }
+        let parsed: Vec<Point> = LineProtocolReader::new(Cursor::new(input), NS).collect::<Result<_, _>>().unwrap();
+        prop_assert_eq!(parsed, points);
+    }
+}
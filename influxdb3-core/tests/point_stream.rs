@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use arrow_array::builder::StringDictionaryBuilder;
+use arrow_array::types::{Int32Type, IntervalMonthDayNanoType};
+use arrow_array::{
+    ArrayRef, BinaryArray, Date32Array, Date64Array, Decimal128Array, Float32Array, Float64Array, Int8Array, Int16Array, Int32Array,
+    Int64Array, IntervalMonthDayNanoArray, LargeStringArray, RecordBatch, StringArray, StringViewArray, TimestampMillisecondArray,
+    TimestampNanosecondArray, TimestampSecondArray, UInt8Array, UInt32Array, UInt64Array,
+};
+use arrow_flight::decode::FlightDataDecoder;
+use arrow_flight::encode::{DictionaryHandling, FlightDataEncoderBuilder};
+use arrow_schema::{DataType, Field, Schema};
+use chrono::{DateTime, TimeZone as _, Utc};
+use futures::TryStreamExt as _;
+use influxdb3_core::{ColumnRole, FieldType, InfluxDBError, Point, PointStream, PointValue, RecordBatchStream, TagName, TimestampPrecision};
+
+/// Sends `batch` through Flight encoding and decoding, the way query results
+/// arrive, and converts it to points.
+async fn try_points(batch: RecordBatch) -> Result<Vec<Point>, InfluxDBError> {
+    PointStream::new(record_batches(batch).await).try_collect().await
+}
+
+async fn record_batches(batch: RecordBatch) -> RecordBatchStream {
+    let flight_data = FlightDataEncoderBuilder::new()
+        .with_dictionary_handling(DictionaryHandling::Resend)
+        .build(futures::stream::iter([Ok(batch)]));
+    RecordBatchStream::new(FlightDataDecoder::new(flight_data)).await.unwrap()
+}
+
+fn column_type(field: Field, column_type: &str) -> Field {
+    field.with_metadata([("iox::column::type".to_string(), column_type.to_string())].into())
+}
+
+async fn points(batch: RecordBatch) -> Vec<Point> {
+    try_points(batch).await.unwrap()
+}
+
+fn batch(columns: Vec<(&str, ArrayRef)>) -> RecordBatch {
+    RecordBatch::try_from_iter(columns).unwrap()
+}
+
+#[tokio::test]
+async fn narrow_numbers() {
+    let points = points(batch(vec![
+        ("i8", Arc::new(Int8Array::from(vec![Some(-8), None])) as ArrayRef),
+        ("i16", Arc::new(Int16Array::from(vec![-16, 0]))),
+        ("i32", Arc::new(Int32Array::from(vec![-32, 0]))),
+        ("u8", Arc::new(UInt8Array::from(vec![8, 0]))),
+        ("u32", Arc::new(UInt32Array::from(vec![32, 0]))),
+        ("f32", Arc::new(Float32Array::from(vec![1.5, 0.0]))),
+    ])).await;
+    let p = &points[0];
+    assert_eq!(p.fields["i8"], PointValue::Integer(-8));
+    assert_eq!(p.fields["i16"], PointValue::Integer(-16));
+    assert_eq!(p.fields["i32"], PointValue::Integer(-32));
+    assert_eq!(p.fields["u8"], PointValue::UInteger(8));
+    assert_eq!(p.fields["u32"], PointValue::UInteger(32));
+    assert_eq!(p.fields["f32"], PointValue::Float(1.5));
+    assert_eq!(points[1].fields["i8"], PointValue::Null);
+}
+
+#[tokio::test]
+async fn strings_and_binary() {
+    let points = points(batch(vec![
+        ("large", Arc::new(LargeStringArray::from(vec!["large"])) as ArrayRef),
+        ("view", Arc::new(StringViewArray::from(vec!["view"]))),
+        ("bin", Arc::new(BinaryArray::from(vec![&b"\x00\x01"[..]]))),
+    ])).await;
+    assert_eq!(points[0].fields["large"], PointValue::String("large".to_string()));
+    assert_eq!(points[0].fields["view"], PointValue::String("view".to_string()));
+    assert_eq!(points[0].fields["bin"], PointValue::Binary(vec![0, 1]));
+}
+
+#[tokio::test]
+async fn dictionary_tags() {
+    let mut host = StringDictionaryBuilder::<Int32Type>::new();
+    host.append_value("a");
+    host.append_null();
+    host.append_value("a");
+    let tag = column_type(
+        Field::new("host", DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)), true),
+        "iox::column_type::tag",
+    );
+    let value = Field::new("v", DataType::Int32, false);
+    let batch = RecordBatch::try_new(
+        Arc::new(Schema::new(vec![tag, value])),
+        vec![Arc::new(host.finish()), Arc::new(Int32Array::from(vec![1, 2, 3]))],
+    ).unwrap();
+
+    let points = points(batch).await;
+    let host = TagName::try_from("host").unwrap();
+    assert_eq!(points[0].tags[&host], "a");
+    assert!(!points[1].tags.contains_key(&host));
+    assert_eq!(points[2].tags[&host], "a");
+}
+
+#[tokio::test]
+async fn dates_decimals_and_intervals() {
+    let decimal = Decimal128Array::from(vec![12345]).with_precision_and_scale(10, 2).unwrap();
+    let interval = IntervalMonthDayNanoArray::from(vec![IntervalMonthDayNanoType::make_value(1, 2, 3)]);
+    let points = points(batch(vec![
+        ("d32", Arc::new(Date32Array::from(vec![19_000])) as ArrayRef),
+        ("d64", Arc::new(Date64Array::from(vec![1_641_600_000_000]))),
+        ("dec", Arc::new(decimal)),
+        ("iv", Arc::new(interval)),
+    ])).await;
+    let p = &points[0];
+    assert_eq!(p.fields["d32"], PointValue::Timestamp(Utc.with_ymd_and_hms(2022, 1, 8, 0, 0, 0).unwrap()));
+    assert_eq!(p.fields["d64"], PointValue::Timestamp(Utc.with_ymd_and_hms(2022, 1, 8, 0, 0, 0).unwrap()));
+    assert_eq!(p.fields["dec"], PointValue::Decimal(12345, 2));
+    assert_eq!(p.fields["iv"], PointValue::Interval(1, 2, 3));
+}
+
+#[tokio::test]
+async fn count_cast_to_int() {
+    // What `SELECT count(*)::int` returns.
+    let points = points(batch(vec![("count", Arc::new(Int32Array::from(vec![42])) as ArrayRef)])).await;
+    assert_eq!(points[0].fields["count"], PointValue::Integer(42));
+}
+
+#[tokio::test]
+async fn unsigned_above_i64_max() {
+    let big = i64::MAX as u64 + 1;
+    let points = points(batch(vec![
+        ("iox::measurement", Arc::new(StringArray::from(vec!["m", "m"])) as ArrayRef),
+        ("u", Arc::new(UInt64Array::from(vec![big, u64::MAX]))),
+    ])).await;
+    assert_eq!(points[0].fields["u"], PointValue::UInteger(big));
+    assert_eq!(points[1].fields["u"], PointValue::UInteger(u64::MAX));
+
+    // And back through line protocol.
+    let line = points[1].to_line_protocol(TimestampPrecision::Nanoseconds).unwrap();
+    let parsed = Point::from_line_protocol(&line, TimestampPrecision::Nanoseconds).unwrap();
+    assert_eq!(parsed.fields["u"], PointValue::UInteger(u64::MAX));
+}
+
+#[tokio::test]
+async fn timestamps_with_time_zones() {
+    let instant = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+    let millis = instant.timestamp_millis();
+    let points = points(batch(vec![
+        ("naive", Arc::new(TimestampMillisecondArray::from(vec![millis])) as ArrayRef),
+        ("offset", Arc::new(TimestampMillisecondArray::from(vec![millis]).with_timezone("+02:00"))),
+        ("named", Arc::new(TimestampMillisecondArray::from(vec![millis]).with_timezone("Europe/Berlin"))),
+    ])).await;
+    for column in ["naive", "offset", "named"] {
+        assert_eq!(points[0].fields[column], PointValue::Timestamp(instant), "{column}");
+    }
+}
+
+#[tokio::test]
+async fn out_of_range_timestamps_are_errors() {
+    let result = try_points(batch(vec![("t", Arc::new(TimestampSecondArray::from(vec![i64::MAX])) as ArrayRef)])).await;
+    assert!(matches!(result, Err(InfluxDBError::InvalidPointValue(..))), "{result:?}");
+}
+
+#[tokio::test]
+async fn column_names_are_kept_as_is() {
+    let points = points(batch(vec![("count(*)", Arc::new(Int64Array::from(vec![3])) as ArrayRef)])).await;
+    assert_eq!(points[0].fields["count(*)"], PointValue::Integer(3));
+}
+
+#[tokio::test]
+async fn result_schema_roles() {
+    let schema = Schema::new(vec![
+        Field::new("iox::measurement", DataType::Utf8, false),
+        column_type(Field::new("host", DataType::Utf8, true), "iox::column_type::tag"),
+        column_type(Field::new("usage", DataType::Float64, true), "iox::column_type::field::float"),
+        column_type(Field::new("count", DataType::UInt64, true), "iox::column_type::field::uinteger"),
+        column_type(Field::new("ts", DataType::Timestamp(arrow_schema::TimeUnit::Nanosecond, None), false), "iox::column_type::timestamp"),
+        Field::new("avg", DataType::Float64, true),
+    ]);
+    let batch = RecordBatch::new_empty(Arc::new(schema));
+
+    let schema = PointStream::new(record_batches(batch).await).result_schema();
+    let roles: Vec<_> = schema.columns().iter().map(|c| (c.name.as_str(), c.role)).collect();
+    assert_eq!(roles, [
+        ("iox::measurement", ColumnRole::Measurement),
+        ("host", ColumnRole::Tag),
+        ("usage", ColumnRole::Field(FieldType::Float)),
+        ("count", ColumnRole::Field(FieldType::UInteger)),
+        ("ts", ColumnRole::Timestamp),
+        ("avg", ColumnRole::Other),
+    ]);
+    assert_eq!(schema.tags().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["host"]);
+    assert_eq!(schema.fields().count(), 2);
+    assert_eq!(schema.time_column().unwrap().name, "ts");
+    assert_eq!(schema.column("usage").unwrap().data_type, DataType::Float64);
+}
+
+fn dictionary(values: &[Option<&str>]) -> ArrayRef {
+    let mut builder = StringDictionaryBuilder::<Int32Type>::new();
+    for value in values {
+        builder.append_option(*value);
+    }
+    Arc::new(builder.finish())
+}
+
+fn dictionary_field(name: &str) -> Field {
+    Field::new(name, DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)), true)
+}
+
+/// `SELECT usage FROM cpu, mem GROUP BY host` as InfluxQL returns it: the
+/// measurement and tags are dictionary encoded and the time column has no
+/// column type.
+fn influxql_result() -> RecordBatch {
+    let schema = Schema::new(vec![
+        dictionary_field("iox::measurement").with_nullable(false),
+        Field::new("time", DataType::Timestamp(arrow_schema::TimeUnit::Nanosecond, None), false),
+        column_type(dictionary_field("host"), "iox::column_type::tag"),
+        column_type(Field::new("usage", DataType::Float64, true), "iox::column_type::field::float"),
+    ]);
+    let columns = vec![
+        dictionary(&[Some("cpu"), Some("cpu"), Some("mem")]),
+        Arc::new(TimestampNanosecondArray::from(vec![1, 2, 3])) as ArrayRef,
+        dictionary(&[Some("a"), None, Some("b")]),
+        Arc::new(Float64Array::from(vec![Some(1.5), Some(2.5), None])),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns).unwrap()
+}
+
+#[tokio::test]
+async fn influxql_dictionary_measurement_and_tags() {
+    let points = points(influxql_result()).await;
+    let host = TagName::try_from("host").unwrap();
+
+    let measurements: Vec<_> = points.iter().map(|p| p.measurement_name.as_str()).collect();
+    assert_eq!(measurements, ["cpu", "cpu", "mem"]);
+    assert_eq!(points[0].tags[&host], "a");
+    assert!(!points[1].tags.contains_key(&host));
+    assert_eq!(points[2].tags[&host], "b");
+    assert_eq!(points[1].time, DateTime::from_timestamp_nanos(2));
+    assert_eq!(points[0].fields["usage"], PointValue::Float(1.5));
+    assert_eq!(points[2].fields["usage"], PointValue::Null);
+    // The measurement and time columns don't show up as fields.
+    assert_eq!(points[0].fields.keys().collect::<Vec<_>>(), ["usage"]);
+}
+
+#[tokio::test]
+async fn influxql_result_schema_roles() {
+    let schema = PointStream::new(record_batches(influxql_result()).await).result_schema();
+    let roles: Vec<_> = schema.columns().iter().map(|c| (c.name.as_str(), c.role)).collect();
+    assert_eq!(roles, [
+        ("iox::measurement", ColumnRole::Measurement),
+        ("time", ColumnRole::Timestamp),
+        ("host", ColumnRole::Tag),
+        ("usage", ColumnRole::Field(FieldType::Float)),
+    ]);
+}
+
+#[tokio::test]
+async fn non_string_measurement_column_is_a_field() {
+    let points = points(batch(vec![
+        ("measurement", Arc::new(Int64Array::from(vec![7])) as ArrayRef),
+        ("v", Arc::new(Int64Array::from(vec![1]))),
+    ])).await;
+    assert_eq!(points[0].measurement_name, "");
+    assert_eq!(points[0].fields["measurement"], PointValue::Integer(7));
+}
+
+#[tokio::test]
+async fn string_view_measurement_column() {
+    let points = points(batch(vec![
+        ("measurement", Arc::new(StringViewArray::from(vec!["cpu"])) as ArrayRef),
+        ("v", Arc::new(Int64Array::from(vec![1]))),
+    ])).await;
+    assert_eq!(points[0].measurement_name, "cpu");
+    assert!(!points[0].fields.contains_key("measurement"));
+}
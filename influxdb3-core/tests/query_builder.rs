@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone as _, Utc};
+use influxdb3_core::{quote_identifier, quote_literal, Aggregate, Order, PointValue, QueryBuilder};
+
+fn time(secs: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(secs, 0).unwrap()
+}
+
+#[test]
+fn quoting() {
+    assert_eq!(quote_identifier("cpu"), r#""cpu""#);
+    assert_eq!(quote_identifier(r#"my "table""#), r#""my ""table""""#);
+    assert_eq!(quote_literal("it's"), "'it''s'");
+}
+
+#[test]
+fn select_all() {
+    let (sql, params) = QueryBuilder::new("Cpu Load").build();
+    assert_eq!(sql, r#"SELECT * FROM "Cpu Load""#);
+    assert!(params.is_empty());
+}
+
+#[test]
+fn filters_are_parameters() {
+    let (sql, params) = QueryBuilder::new("cpu")
+        .select("time")
+        .select("usage")
+        .time_range(time(10)..time(20))
+        .tag_eq("host", "a'; DROP TABLE cpu; --")
+        .tag_in("region", ["eu", "us"])
+        .order_by("time", Order::Desc)
+        .limit(10)
+        .build();
+    assert_eq!(
+        sql,
+        r#"SELECT "time", "usage" FROM "cpu" WHERE time >= $p0 AND time < $p1 AND "host" = $p2 AND "region" IN ($p3, $p4) ORDER BY "time" DESC LIMIT 10"#,
+    );
+    assert_eq!(params, vec![
+        ("p0".to_string(), PointValue::Timestamp(time(10))),
+        ("p1".to_string(), PointValue::Timestamp(time(20))),
+        ("p2".to_string(), PointValue::String("a'; DROP TABLE cpu; --".to_string())),
+        ("p3".to_string(), PointValue::String("eu".to_string())),
+        ("p4".to_string(), PointValue::String("us".to_string())),
+    ]);
+}
+
+#[test]
+fn time_range_bounds() {
+    let (sql, params) = QueryBuilder::new("cpu").time_range(time(10)..=time(20)).build();
+    assert_eq!(sql, r#"SELECT * FROM "cpu" WHERE time >= $p0 AND time <= $p1"#);
+    assert_eq!(params.len(), 2);
+
+    let (sql, params) = QueryBuilder::new("cpu").time_range(time(10)..).build();
+    assert_eq!(sql, r#"SELECT * FROM "cpu" WHERE time >= $p0"#);
+    assert_eq!(params.len(), 1);
+
+    let (sql, params) = QueryBuilder::new("cpu").time_range(..).build();
+    assert_eq!(sql, r#"SELECT * FROM "cpu""#);
+    assert!(params.is_empty());
+}
+
+#[test]
+fn empty_tag_in_matches_nothing() {
+    let (sql, params) = QueryBuilder::new("cpu").tag_in("host", Vec::<String>::new()).build();
+    assert_eq!(sql, r#"SELECT * FROM "cpu" WHERE FALSE"#);
+    assert!(params.is_empty());
+}
+
+#[test]
+fn date_bin_aggregates() {
+    let (sql, _) = QueryBuilder::new("cpu")
+        .date_bin(Duration::from_millis(1500))
+        .aggregate(Aggregate::Max, "usage", "max_usage")
+        .aggregate(Aggregate::Count, "usage", "n")
+        .order_by("time", Order::Asc)
+        .build();
+    assert_eq!(
+        sql,
+        r#"SELECT date_bin(INTERVAL '1500000000 nanoseconds', time) AS "time", max("usage") AS "max_usage", count("usage") AS "n" FROM "cpu" GROUP BY date_bin(INTERVAL '1500000000 nanoseconds', time) ORDER BY "time" ASC"#,
+    );
+}
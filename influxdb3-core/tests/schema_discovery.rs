@@ -0,0 +1,174 @@
+mod common;
+
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{TimeZone as _, Utc};
+use influxdb3_core::{ColumnRole, FieldKey, FieldType, InfluxDBError};
+use serde_json::json;
+
+use common::query_server::QueryServer;
+
+fn strings(columns: &[(&str, Vec<Option<&str>>)]) -> RecordBatch {
+    RecordBatch::try_from_iter(
+        columns.iter().map(|(name, values)| (*name, Arc::new(StringArray::from(values.clone())) as ArrayRef)),
+    ).unwrap()
+}
+
+fn column_type(field: Field, column_type: &str) -> Field {
+    field.with_metadata([("iox::column::type".to_string(), column_type.to_string())].into())
+}
+
+/// `information_schema.columns` rows for a `cpu` table.
+fn cpu_columns() -> RecordBatch {
+    strings(&[
+        ("column_name", vec![Some("host"), Some("time"), Some("usage"), Some("count"), Some("up"), Some("note"), Some("total")]),
+        ("is_nullable", vec![Some("YES"), Some("NO"), Some("YES"), Some("YES"), Some("YES"), Some("YES"), Some("YES")]),
+    ])
+}
+
+/// The `cpu` table as `SELECT * LIMIT 0` returns it, in a different order.
+fn cpu_schema() -> RecordBatch {
+    let dictionary = DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8));
+    let schema = Schema::new(vec![
+        column_type(Field::new("count", DataType::Int64, true), "iox::column_type::field::integer"),
+        column_type(Field::new("host", dictionary, true), "iox::column_type::tag"),
+        column_type(Field::new("note", DataType::Utf8, true), "iox::column_type::field::string"),
+        column_type(Field::new("time", DataType::Timestamp(TimeUnit::Nanosecond, None), false), "iox::column_type::timestamp"),
+        column_type(Field::new("total", DataType::UInt64, true), "iox::column_type::field::uinteger"),
+        column_type(Field::new("up", DataType::Boolean, true), "iox::column_type::field::boolean"),
+        column_type(Field::new("usage", DataType::Float64, true), "iox::column_type::field::float"),
+    ]);
+    RecordBatch::new_empty(Arc::new(schema))
+}
+
+#[tokio::test]
+async fn list_tables() {
+    let (server, client) = QueryServer::start().await;
+    server.respond(strings(&[("table_name", vec![Some("cpu"), Some("mem")])]));
+
+    assert_eq!(client.list_tables().await.unwrap(), ["cpu", "mem"]);
+    let ticket = &server.tickets()[0];
+    assert!(ticket["sql_query"].as_str().unwrap().contains("information_schema.tables"), "{ticket}");
+}
+
+#[tokio::test]
+async fn list_columns() {
+    let (server, client) = QueryServer::start().await;
+    server.respond(cpu_columns());
+    server.respond(cpu_schema());
+
+    let columns = client.list_columns("cpu").await.unwrap();
+    let roles: Vec<_> = columns.iter().map(|c| (c.name.as_str(), c.role)).collect();
+    assert_eq!(roles, [
+        ("host", ColumnRole::Tag),
+        ("time", ColumnRole::Timestamp),
+        ("usage", ColumnRole::Field(FieldType::Float)),
+        ("count", ColumnRole::Field(FieldType::Integer)),
+        ("up", ColumnRole::Field(FieldType::Boolean)),
+        ("note", ColumnRole::Field(FieldType::String)),
+        ("total", ColumnRole::Field(FieldType::UInteger)),
+    ]);
+    assert_eq!(columns[1].data_type, DataType::Timestamp(TimeUnit::Nanosecond, None));
+    assert!(columns[0].nullable);
+    assert!(!columns[1].nullable);
+
+    let tickets = server.tickets();
+    assert!(tickets[0]["sql_query"].as_str().unwrap().contains("information_schema.columns"), "{}", tickets[0]);
+    assert_eq!(tickets[0]["params"], json!({"table": "cpu"}));
+    assert_eq!(tickets[1]["sql_query"], r#"SELECT * FROM "cpu" LIMIT 0"#);
+}
+
+#[tokio::test]
+async fn list_columns_takes_roles_from_column_types() {
+    let (server, client) = QueryServer::start().await;
+    server.respond(strings(&[
+        ("column_name", vec![Some("region"), Some("label"), Some("time")]),
+        ("is_nullable", vec![Some("YES"), Some("YES"), Some("NO")]),
+    ]));
+    // A tag that isn't a dictionary and a string field have the same Arrow
+    // type and are told apart by their column type.
+    let schema = Schema::new(vec![
+        column_type(Field::new("region", DataType::Utf8, true), "iox::column_type::tag"),
+        column_type(Field::new("label", DataType::Utf8, true), "iox::column_type::field::string"),
+        column_type(Field::new("time", DataType::Timestamp(TimeUnit::Nanosecond, None), false), "iox::column_type::timestamp"),
+    ]);
+    server.respond(RecordBatch::new_empty(Arc::new(schema)));
+
+    let columns = client.list_columns("cpu").await.unwrap();
+    let roles: Vec<_> = columns.iter().map(|c| (c.name.as_str(), c.role)).collect();
+    assert_eq!(roles, [
+        ("region", ColumnRole::Tag),
+        ("label", ColumnRole::Field(FieldType::String)),
+        ("time", ColumnRole::Timestamp),
+    ]);
+}
+
+#[tokio::test]
+async fn list_columns_of_a_missing_table_is_empty() {
+    let (server, client) = QueryServer::start().await;
+    server.respond(strings(&[("column_name", vec![]), ("is_nullable", vec![])]));
+
+    assert!(client.list_columns("nope").await.unwrap().is_empty());
+    assert_eq!(server.tickets().len(), 1);
+}
+
+#[tokio::test]
+async fn list_columns_fails_if_the_table_query_fails() {
+    let (server, client) = QueryServer::start().await;
+    server.respond(cpu_columns());
+
+    let err = client.list_columns("cpu").await.unwrap_err();
+    assert!(matches!(err, InfluxDBError::GrpcError(_) | InfluxDBError::FlightError(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn tag_keys() {
+    let (server, client) = QueryServer::start().await;
+    server.respond(cpu_columns());
+    server.respond(cpu_schema());
+
+    assert_eq!(client.tag_keys("cpu").await.unwrap(), ["host"]);
+}
+
+#[tokio::test]
+async fn field_keys() {
+    let (server, client) = QueryServer::start().await;
+    server.respond(cpu_columns());
+    server.respond(cpu_schema());
+
+    let field = |name: &str, field_type| FieldKey { name: name.to_string(), field_type };
+    assert_eq!(client.field_keys("cpu").await.unwrap(), [
+        field("usage", FieldType::Float),
+        field("count", FieldType::Integer),
+        field("up", FieldType::Boolean),
+        field("note", FieldType::String),
+        field("total", FieldType::UInteger),
+    ]);
+}
+
+#[tokio::test]
+async fn tag_values() {
+    let (server, client) = QueryServer::start().await;
+    server.respond(strings(&[("host", vec![Some("a"), None, Some("b")])]));
+    server.respond(strings(&[("host", vec![Some("a")])]));
+
+    assert_eq!(client.tag_values("cpu", "host", ..).await.unwrap(), ["a", "b"]);
+
+    let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap();
+    assert_eq!(client.tag_values("cpu", "host", start..end).await.unwrap(), ["a"]);
+
+    let tickets = server.tickets();
+    assert_eq!(
+        tickets[0]["sql_query"],
+        r#"SELECT DISTINCT "host" FROM "cpu" WHERE "host" IS NOT NULL ORDER BY "host""#,
+    );
+    assert_eq!(tickets[0]["params"], json!({}));
+    assert_eq!(
+        tickets[1]["sql_query"],
+        r#"SELECT DISTINCT "host" FROM "cpu" WHERE "host" IS NOT NULL AND time >= $start AND time < $end ORDER BY "host""#,
+    );
+    assert_eq!(tickets[1]["params"], json!({"start": "2024-01-01T00:00:00Z", "end": "2024-01-02T00:00:00Z"}));
+}
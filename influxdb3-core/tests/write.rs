@@ -0,0 +1,170 @@
+mod common;
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use chrono::DateTime;
+use influxdb3_core::{InfluxDBError, Point, RetryPolicy, SpoolOptions, TagName};
+use tokio::io::{AsyncRead, ReadBuf};
+
+use common::WriteServer;
+
+fn point(i: i64) -> Point {
+    let mut p = Point::new_with_measurement("m");
+    p.set_field("v", i);
+    p.set_timestamp(DateTime::from_timestamp_nanos(i));
+    p
+}
+
+fn quick_retries() -> RetryPolicy {
+    RetryPolicy::new().max_attempts(3).base_delay(Duration::ZERO)
+}
+
+#[tokio::test]
+async fn server_errors_are_retried() {
+    let server = WriteServer::start().await;
+    server.respond(503, r#"{"error": "unavailable"}"#);
+    server.respond(500, r#"{"error": "internal"}"#);
+    let client = server.client().retry_policy(quick_retries()).build().unwrap();
+
+    client.write_points([point(1)]).await.unwrap();
+    assert_eq!(server.bodies(), ["m v=1i 1\n"; 3]);
+}
+
+#[tokio::test]
+async fn retries_give_up_with_the_last_server_error() {
+    let server = WriteServer::start().await;
+    for _ in 0..3 {
+        server.respond(503, r#"{"error": "unavailable"}"#);
+    }
+    let client = server.client().retry_policy(quick_retries()).build().unwrap();
+
+    let err = client.write_points([point(1)]).await.unwrap_err();
+    assert!(matches!(err, InfluxDBError::ServerError(503, message) if message == "unavailable"));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let server = WriteServer::start().await;
+    server.respond(400, r#"{"error": "bad request"}"#);
+    let client = server.client().retry_policy(quick_retries()).build().unwrap();
+
+    let err = client.write_points([point(1)]).await.unwrap_err();
+    assert!(matches!(err, InfluxDBError::ApiError(message) if message == "bad request"));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn rejected_lines_map_to_points_across_batches() {
+    let server = WriteServer::start().await;
+    server.respond(400, r#"{"error": "partial write", "data": [{"original_line": "m v=2i 2", "line_number": 1, "error_message": "bad"}]}"#);
+    server.respond(400, r#"{"error": "partial write", "data": [{"original_line": "m v=5i 5", "line_number": 2, "error_message": "bad"}]}"#);
+    let client = server.client().max_batch_lines(2).build().unwrap();
+
+    let err = client.write_points((2..6).map(point)).await.unwrap_err();
+    let InfluxDBError::PartialWrite(e) = err else {
+        panic!("expected a partial write error, got {err:?}");
+    };
+    let indexes: Vec<_> = e.lines.iter().map(|l| l.point_index).collect();
+    assert_eq!(indexes, [Some(0), Some(3)]);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn call_tags_are_merged_with_client_default_tags() {
+    let server = WriteServer::start().await;
+    let client_tags = [("host", "a"), ("region", "eu")].map(|(k, v)| (TagName::try_from(k).unwrap(), v.to_string())).into();
+    let client = server.client().default_tags(client_tags).build().unwrap();
+
+    let call_tags = [("region", "us"), ("rack", "1")].map(|(k, v)| (TagName::try_from(k).unwrap(), v.to_string())).into();
+    client.write_points_with_tags([point(1)], &call_tags).await.unwrap();
+    assert_eq!(server.bodies(), ["m,host=a,rack=1,region=us v=1i 1\n"]);
+}
+
+#[tokio::test]
+async fn spool_holds_batches_until_the_server_is_back() {
+    let server = WriteServer::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    server.respond(503, r#"{"error": "unavailable"}"#);
+    let client = server.client().spool(SpoolOptions::new(dir.path())).build().unwrap();
+
+    client.write_points([point(1)]).await.unwrap();
+    client.write_points([point(2)]).await.unwrap();
+    assert_eq!(server.bodies(), ["m v=1i 1\n", "m v=1i 1\n", "m v=2i 2\n"]);
+    assert_eq!(client.drain_spool().await.unwrap().sent, 0);
+}
+
+#[tokio::test]
+async fn spooled_batch_refused_for_good_is_dropped() {
+    let server = WriteServer::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    server.respond(503, r#"{"error": "unavailable"}"#);
+    server.respond(400, r#"{"error": "database not found"}"#);
+    let client = server.client().spool(SpoolOptions::new(dir.path())).build().unwrap();
+
+    client.write_points([point(1)]).await.unwrap();
+    client.write_points([point(2)]).await.unwrap();
+    assert_eq!(server.bodies(), ["m v=1i 1\n", "m v=1i 1\n", "m v=2i 2\n"]);
+
+    client.write_points([point(3)]).await.unwrap();
+    assert_eq!(server.bodies().last().unwrap(), "m v=3i 3\n");
+    assert_eq!(server.requests().len(), 4);
+
+    let report = client.drain_spool().await.unwrap();
+    assert!(matches!(&report.dropped[..], [InfluxDBError::ApiError(message)] if message == "database not found"), "{report:?}");
+    assert!(client.drain_spool().await.unwrap().dropped.is_empty());
+}
+
+#[tokio::test]
+async fn batch_refused_for_good_is_returned_instead_of_spooled() {
+    let server = WriteServer::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    server.respond(401, r#"{"error": "unauthorized"}"#);
+    let client = server.client().spool(SpoolOptions::new(dir.path())).build().unwrap();
+
+    assert!(matches!(client.write_points([point(1)]).await, Err(InfluxDBError::ApiError(_))));
+    assert_eq!(client.drain_spool().await.unwrap().sent, 0);
+    assert_eq!(server.requests().len(), 1);
+}
+
+/// Hands out `chunk` bytes per read, to split lines at every position.
+struct Chunked {
+    data: &'static [u8],
+    chunk: usize,
+}
+
+impl AsyncRead for Chunked {
+    fn poll_read(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let n = self.chunk.min(self.data.len()).min(buf.remaining());
+        buf.put_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Poll::Ready(Ok(()))
+    }
+}
+
+const LP: &[u8] = b"# a \"comment\n\
+    cpu,host=a usage=1.5 1\r\n\
+    \n\
+    log msg=\"two\nlines \\\" here\" 2\n\
+    cpu,host=b usage=2.5 3";
+
+#[tokio::test]
+async fn write_lp_reader_batches_match_write_lp_for_every_chunk_size() {
+    let server = WriteServer::start().await;
+    let client = server.client().max_batch_lines(2).build().unwrap();
+
+    client.write_lp(LP).await.unwrap();
+    let expected = server.bodies();
+    assert_eq!(expected, [
+        "cpu,host=a usage=1.5 1\nlog msg=\"two\nlines \\\" here\" 2\n",
+        "cpu,host=b usage=2.5 3\n",
+    ]);
+
+    for chunk in 1..=LP.len() {
+        let before = server.requests().len();
+        client.write_lp_reader(Chunked { data: LP, chunk }).await.unwrap();
+        assert_eq!(server.bodies()[before..], expected, "chunk size {chunk}");
+    }
+}
@@ -0,0 +1,250 @@
+mod common;
+
+use std::time::Duration;
+
+use chrono::DateTime;
+use futures::SinkExt;
+use influxdb3_core::{InfluxDBError, Point, SpoolOptions, WriteApiOptions, WriteOptions};
+use tokio::sync::mpsc;
+
+use common::WriteServer;
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+fn point(i: i64) -> Point {
+    let mut p = Point::new_with_measurement("m");
+    p.set_field("v", i);
+    p.set_timestamp(DateTime::from_timestamp_nanos(i));
+    p
+}
+
+fn lines(bodies: &[String]) -> Vec<&str> {
+    bodies.iter().flat_map(|body| body.lines()).collect()
+}
+
+#[tokio::test]
+async fn flushes_when_max_lines_is_reached() {
+    let server = WriteServer::start().await;
+    let client = server.client().build().unwrap();
+    let api = client.write_api(WriteApiOptions::new().max_lines(2).flush_interval(HOUR));
+
+    api.write_points([point(1), point(2), point(3)]).await.unwrap();
+    let requests = server.wait_for_requests(1).await;
+    assert_eq!(requests[0].body, "m v=1i 1\nm v=2i 2\n");
+
+    // The third point waits for the next flush.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(server.requests().len(), 1);
+    api.close().await.unwrap();
+    assert_eq!(server.bodies(), ["m v=1i 1\nm v=2i 2\n", "m v=3i 3\n"]);
+}
+
+#[tokio::test]
+async fn flushes_when_max_bytes_is_reached() {
+    let server = WriteServer::start().await;
+    let client = server.client().build().unwrap();
+    let api = client.write_api(WriteApiOptions::new().max_bytes(9).flush_interval(HOUR));
+
+    api.write(point(1)).await.unwrap();
+    let requests = server.wait_for_requests(1).await;
+    assert_eq!(requests[0].body, "m v=1i 1\n");
+}
+
+#[tokio::test]
+async fn flushes_on_the_interval() {
+    let server = WriteServer::start().await;
+    let client = server.client().build().unwrap();
+    let api = client.write_api(WriteApiOptions::new().flush_interval(Duration::from_millis(50)));
+
+    api.write(point(1)).await.unwrap();
+    assert!(server.requests().is_empty());
+    let requests = server.wait_for_requests(1).await;
+    assert_eq!(requests[0].body, "m v=1i 1\n");
+}
+
+#[tokio::test]
+async fn close_writes_everything_queued() {
+    let server = WriteServer::start().await;
+    let client = server.client().build().unwrap();
+    let api = client.write_api(WriteApiOptions::new().flush_interval(HOUR));
+    let other = api.clone();
+
+    api.write_points((0..100).map(point)).await.unwrap();
+    api.close().await.unwrap();
+
+    let bodies = server.bodies();
+    assert_eq!(lines(&bodies).len(), 100);
+    assert!(matches!(other.write(point(0)).await, Err(InfluxDBError::WriteApiClosed)));
+    assert!(matches!(other.flush().await, Err(InfluxDBError::WriteApiClosed)));
+}
+
+#[tokio::test]
+async fn background_errors_go_to_the_error_handler() {
+    let server = WriteServer::start().await;
+    server.respond(400, r#"{"error": "bad request"}"#);
+    let client = server.client().build().unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let api = client.write_api(
+        WriteApiOptions::new()
+            .max_lines(1)
+            .flush_interval(HOUR)
+            .error_handler(move |e| tx.send(e).unwrap()),
+    );
+
+    api.write(point(1)).await.unwrap();
+    let err = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+    assert!(matches!(err, InfluxDBError::ApiError(message) if message == "bad request"));
+
+    // The error was handled, so it is not returned again.
+    api.write(point(2)).await.unwrap();
+    api.flush().await.unwrap();
+    assert_eq!(server.bodies(), ["m v=1i 1\n", "m v=2i 2\n"]);
+}
+
+#[tokio::test]
+async fn background_errors_without_a_handler_are_returned_by_flush() {
+    let server = WriteServer::start().await;
+    server.respond(400, r#"{"error": "bad request"}"#);
+    let client = server.client().build().unwrap();
+    let api = client.write_api(WriteApiOptions::new().max_lines(1).flush_interval(HOUR));
+
+    api.write(point(1)).await.unwrap();
+    server.wait_for_requests(1).await;
+    assert!(matches!(api.flush().await, Err(InfluxDBError::ApiError(_))));
+    api.flush().await.unwrap();
+}
+
+#[tokio::test]
+async fn explicit_flush_returns_its_own_error() {
+    let server = WriteServer::start().await;
+    server.respond(400, r#"{"error": "bad request"}"#);
+    let client = server.client().build().unwrap();
+    let api = client.write_api(WriteApiOptions::new().flush_interval(HOUR));
+
+    api.write(point(1)).await.unwrap();
+    assert!(matches!(api.flush().await, Err(InfluxDBError::ApiError(_))));
+}
+
+#[tokio::test]
+async fn sink_flush_waits_for_the_write() {
+    let server = WriteServer::start().await;
+    let client = server.client().build().unwrap();
+    let mut api = client.write_api(WriteApiOptions::new().flush_interval(HOUR));
+
+    // `send` flushes the sink after queueing the point.
+    api.send(point(1)).await.unwrap();
+    assert_eq!(server.bodies(), ["m v=1i 1\n"]);
+
+    api.feed(point(2)).await.unwrap();
+    api.feed(point(3)).await.unwrap();
+    assert_eq!(server.requests().len(), 1);
+    SinkExt::<Point>::flush(&mut api).await.unwrap();
+    assert_eq!(server.bodies(), ["m v=1i 1\n", "m v=2i 2\nm v=3i 3\n"]);
+}
+
+#[tokio::test]
+async fn sink_flush_reports_write_errors() {
+    let server = WriteServer::start().await;
+    server.respond(400, r#"{"error": "bad request"}"#);
+    let client = server.client().build().unwrap();
+    let mut api = client.write_api(WriteApiOptions::new().flush_interval(HOUR));
+
+    assert!(matches!(api.send(point(1)).await, Err(InfluxDBError::ApiError(_))));
+}
+
+#[tokio::test]
+async fn sink_close_writes_and_leaves_other_handles_open() {
+    let server = WriteServer::start().await;
+    let client = server.client().build().unwrap();
+    let api = client.write_api(WriteApiOptions::new().flush_interval(HOUR));
+    let mut sink = api.clone();
+
+    sink.feed(point(1)).await.unwrap();
+    SinkExt::<Point>::close(&mut sink).await.unwrap();
+    assert_eq!(server.bodies(), ["m v=1i 1\n"]);
+
+    api.write(point(2)).await.unwrap();
+    api.close().await.unwrap();
+    assert_eq!(server.bodies(), ["m v=1i 1\n", "m v=2i 2\n"]);
+}
+
+#[tokio::test]
+async fn failed_batch_does_not_hold_back_the_ones_after_it() {
+    let server = WriteServer::start().await;
+    server.respond(400, r#"{"error": "bad request"}"#);
+    let client = server.client().build().unwrap();
+    // The second line doesn't fit in the first request, so one flush sends
+    // two batches.
+    let api = client.write_api(
+        WriteApiOptions::new()
+            .flush_interval(HOUR)
+            .write_options(WriteOptions::new().max_bytes(12)),
+    );
+
+    api.write_points([point(1), point(2)]).await.unwrap();
+    server.wait_for_requests(2).await;
+    assert!(matches!(api.flush().await, Err(InfluxDBError::ApiError(_))));
+    assert_eq!(server.bodies(), ["m v=1i 1\n", "m v=2i 2\n"]);
+}
+
+#[tokio::test]
+async fn every_failed_batch_is_reported() {
+    let server = WriteServer::start().await;
+    server.respond(400, r#"{"error": "first"}"#);
+    server.respond(400, r#"{"error": "second"}"#);
+    let client = server.client().build().unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let api = client.write_api(
+        WriteApiOptions::new()
+            .flush_interval(HOUR)
+            .write_options(WriteOptions::new().max_bytes(12))
+            .error_handler(move |e| tx.send(e).unwrap()),
+    );
+
+    // The second point fills the first request, and both batches fail in the
+    // same background flush.
+    api.write_points([point(1), point(2)]).await.unwrap();
+    api.flush().await.unwrap();
+    let errors: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+    assert!(matches!(&errors[..], [InfluxDBError::ApiError(first), InfluxDBError::ApiError(second)]
+        if first == "first" && second == "second"), "{errors:?}");
+}
+
+#[tokio::test]
+async fn flush_queued_behind_close_gets_its_result() {
+    let server = WriteServer::start().await;
+    let client = server.client().build().unwrap();
+    let api = client.write_api(WriteApiOptions::new().flush_interval(HOUR));
+    let other = api.clone();
+
+    api.write(point(1)).await.unwrap();
+    // Both requests are queued before the background task sees either.
+    let (closed, flushed) = futures::join!(api.close(), other.flush());
+    closed.unwrap();
+    flushed.unwrap();
+    assert_eq!(server.bodies(), ["m v=1i 1\n"]);
+}
+
+#[tokio::test]
+async fn dropped_spooled_batches_go_to_the_error_handler() {
+    let server = WriteServer::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    server.respond(503, r#"{"error": "unavailable"}"#);
+    server.respond(400, r#"{"error": "database not found"}"#);
+    let client = server.client().spool(SpoolOptions::new(dir.path())).build().unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let api = client.write_api(
+        WriteApiOptions::new()
+            .flush_interval(Duration::from_millis(50))
+            .error_handler(move |e| tx.send(e).unwrap()),
+    );
+
+    // The first point is spooled, then refused for good ahead of the second.
+    api.write(point(1)).await.unwrap();
+    api.flush().await.unwrap();
+    api.write(point(2)).await.unwrap();
+    api.flush().await.unwrap();
+    let err = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+    assert!(matches!(err, InfluxDBError::ApiError(message) if message == "database not found"));
+    assert_eq!(server.bodies(), ["m v=1i 1\n", "m v=1i 1\n", "m v=2i 2\n"]);
+}
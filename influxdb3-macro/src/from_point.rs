@@ -2,20 +2,12 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{DeriveInput, Error, spanned::Spanned};
 
-use crate::parser::{FieldType, parse_struct};
+use crate::parser::{FieldType, check_ignored_fields, parse_struct};
 
 pub fn derive_from_point_impl(input: DeriveInput) -> Result<TokenStream, Error> {
     let struct_info = parse_struct(&input)?;
     let struct_name = struct_info.struct_name;
-
-    for info in &struct_info.fields {
-        if info.ignore && !info.use_default {
-            return Err(Error::new(
-                info.field_name.span(),
-                "Ignored fields must also be marked with #[influxdb(default)]"
-            ));
-        }
-    }
+    check_ignored_fields(&struct_info)?;
 
     let mut field_extractions = Vec::new();
     let mut tag_extractions = Vec::new();
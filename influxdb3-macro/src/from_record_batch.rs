@@ -0,0 +1,123 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{DeriveInput, Error};
+
+use crate::parser::{FieldType, StructInfo, check_ignored_fields, parse_struct};
+
+pub fn derive_from_record_batch_impl(input: DeriveInput) -> Result<TokenStream, Error> {
+    let struct_info = parse_struct(&input)?;
+    check_ignored_fields(&struct_info)?;
+    let struct_name = struct_info.struct_name;
+    let body = from_batch_body(&struct_info);
+
+    let expanded = quote! {
+        impl influxdb3_core::FromRecordBatch for #struct_name {
+            fn from_record_batch(batch: &influxdb3_core::RecordBatch) -> Vec<Result<Self, influxdb3_core::InfluxDBError>> {
+                #body
+            }
+        }
+    };
+
+    Ok(expanded)
+}
+
+/// Decodes each column the struct uses once, then builds the rows from the
+/// decoded values. Missing columns and nulls are handled the same way as
+/// missing tags and fields in `FromPoint`. Every column moves on by one value
+/// per row before any of them is checked, so an error stays with its row.
+fn from_batch_body(struct_info: &StructInfo<'_>) -> TokenStream {
+    let mut columns = Vec::new();
+    let mut row_values = Vec::new();
+    let mut values = Vec::new();
+
+    for (i, info) in struct_info.fields.iter().enumerate() {
+        let field_name = &info.field_name;
+        if info.ignore {
+            values.push(quote! {
+                #field_name: Default::default()
+            });
+            continue;
+        }
+
+        let column = format_ident!("column_{}", i);
+        let row_value = format_ident!("value_{}", i);
+        let point_name = info.rename.clone().unwrap_or_else(|| field_name.to_string());
+        let field_ty = &info.ty;
+
+        row_values.push(quote! {
+            let #row_value = #column.as_mut().and_then(Iterator::next);
+        });
+
+        match info.field_type {
+            FieldType::Time => {
+                columns.push(quote! {
+                    let mut #column = influxdb3_core::decode_time_column(batch).map(Vec::into_iter);
+                });
+                values.push(quote! {
+                    #field_name: #row_value.transpose()?.flatten().unwrap_or_default()
+                });
+            }
+            FieldType::Tag => {
+                columns.push(quote! {
+                    let mut #column = influxdb3_core::decode_column::<String>(batch, #point_name).map(Vec::into_iter);
+                });
+                let value = quote! {
+                    #row_value.transpose()?.flatten()
+                        .map(|s| s.parse::<#field_ty>()
+                            .map_err(|_| influxdb3_core::InfluxDBError::Other(
+                                format!("Failed to parse tag '{}' as {}", #point_name, stringify!(#field_ty))
+                            )))
+                        .transpose()?
+                };
+                if info.use_default {
+                    values.push(quote! {
+                        #field_name: #value.unwrap_or_default()
+                    });
+                } else {
+                    values.push(quote! {
+                        #field_name: #value
+                            .ok_or_else(|| influxdb3_core::InfluxDBError::Other(
+                                format!("Missing required tag: {}", #point_name)
+                            ))?
+                    });
+                }
+            }
+            FieldType::Field => {
+                columns.push(quote! {
+                    let mut #column = influxdb3_core::decode_column::<#field_ty>(batch, #point_name).map(Vec::into_iter);
+                });
+                let value = quote! {
+                    #row_value.transpose()
+                        .map_err(|e| influxdb3_core::InfluxDBError::Other(
+                            format!("Failed to convert field '{}': {:?}", #point_name, e)
+                        ))?
+                        .flatten()
+                };
+                if info.use_default {
+                    values.push(quote! {
+                        #field_name: #value.unwrap_or_default()
+                    });
+                } else {
+                    values.push(quote! {
+                        #field_name: #value
+                            .ok_or_else(|| influxdb3_core::InfluxDBError::Other(
+                                format!("Missing required field: {}", #point_name)
+                            ))?
+                    });
+                }
+            }
+        }
+    }
+
+    quote! {
+        #(#columns)*
+        (0..batch.num_rows())
+            .map(|_| -> Result<Self, influxdb3_core::InfluxDBError> {
+                #(#row_values)*
+                Ok(Self {
+                    #(#values,)*
+                })
+            })
+            .collect()
+    }
+}
@@ -3,6 +3,7 @@ use syn::{parse_macro_input, DeriveInput};
 
 mod to_point;
 mod from_point;
+mod from_record_batch;
 mod parser;
 mod util;
 
@@ -20,4 +21,12 @@ pub fn derive_from_point(input: TokenStream) -> TokenStream {
     from_point::derive_from_point_impl(input)
         .unwrap_or_else(|err| err.to_compile_error())
         .into()
-}
\ No newline at end of file
+}
+
+#[proc_macro_derive(FromRecordBatch, attributes(influxdb))]
+pub fn derive_from_record_batch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_record_batch::derive_from_record_batch_impl(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
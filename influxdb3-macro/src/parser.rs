@@ -42,14 +42,10 @@ pub(crate) fn parse_struct(input: &'_ DeriveInput) -> Result<StructInfo<'_>, Err
     for attr in &input.attrs {
         if attr.path().is_ident("influxdb") {
             attr.parse_nested_meta(|meta| {
-                if meta.path.is_ident("measurement") {
-                    if let Ok(value) = meta.value() {
-                        if let Ok(lit) = value.parse::<Lit>() {
-                            if let Lit::Str(s) = lit {
-                                measurement_name = Some(s.value());
-                            }
-                        }
-                    }
+                if meta.path.is_ident("measurement")
+                    && let Ok(value) = meta.value()
+                    && let Ok(Lit::Str(s)) = value.parse::<Lit>() {
+                    measurement_name = Some(s.value());
                 }
                 Ok(())
             })?;
@@ -68,6 +64,19 @@ pub(crate) fn parse_struct(input: &'_ DeriveInput) -> Result<StructInfo<'_>, Err
     })
 }
 
+/// `FromPoint` and `FromRecordBatch` fill ignored fields with their default.
+pub(crate) fn check_ignored_fields(struct_info: &StructInfo<'_>) -> Result<(), Error> {
+    for info in &struct_info.fields {
+        if info.ignore && !info.use_default {
+            return Err(Error::new(
+                info.field_name.span(),
+                "Ignored fields must also be marked with #[influxdb(default)]"
+            ));
+        }
+    }
+    Ok(())
+}
+
 fn parse_fields(fields: &Fields) -> Result<Vec<FieldInfo>, Error> {
     let fields = match fields {
         Fields::Named(fields) => &fields.named,
@@ -108,12 +117,9 @@ fn parse_fields(fields: &Fields) -> Result<Vec<FieldInfo>, Error> {
                         is_tag_attr = true;
                         Ok(())
                     } else if meta.path.is_ident("rename") {
-                        if let Ok(value) = meta.value() {
-                            if let Ok(lit) = value.parse::<Lit>() {
-                                if let Lit::Str(s) = lit {
-                                    rename = Some(s.value());
-                                }
-                            }
+                        if let Ok(value) = meta.value()
+                            && let Ok(Lit::Str(s)) = value.parse::<Lit>() {
+                            rename = Some(s.value());
                         }
                         Ok(())
                     } else if meta.path.is_ident("default") {
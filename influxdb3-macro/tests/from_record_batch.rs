@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use arrow_array::builder::StringDictionaryBuilder;
+use arrow_array::types::Int32Type;
+use arrow_array::{ArrayRef, Float64Array, Int32Array, Int64Array, RecordBatch, StringArray, TimestampNanosecondArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use chrono::{DateTime, Utc};
+use influxdb3_core::{FromRecordBatch as _, InfluxDBError, ViaPoint};
+use influxdb3_macro::{FromPoint, FromRecordBatch};
+
+/// Decoded by column, or through `FromPoint` a row at a time.
+#[derive(Debug, PartialEq, FromPoint, FromRecordBatch)]
+struct Cpu {
+    time: DateTime<Utc>,
+    #[influxdb(tag)]
+    host: String,
+    usage: f64,
+    #[influxdb(rename = "cores")]
+    core_count: i32,
+    idle: Option<i64>,
+    #[influxdb(default)]
+    missing: u64,
+    #[influxdb(ignore, default)]
+    note: String,
+}
+
+#[derive(Debug, PartialEq, FromRecordBatch)]
+struct Count {
+    count: i64,
+}
+
+#[derive(Debug, PartialEq, FromRecordBatch)]
+struct Pair {
+    small: u8,
+    count: i64,
+}
+
+fn rows<T>(batch: &RecordBatch) -> Result<Vec<T>, InfluxDBError>
+where
+    T: influxdb3_core::FromRecordBatch,
+{
+    T::from_record_batch(batch).into_iter().collect()
+}
+
+fn cpu_batch() -> RecordBatch {
+    let mut host = StringDictionaryBuilder::<Int32Type>::new();
+    host.append_value("a");
+    host.append_value("b");
+    let tag = Field::new("host", DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)), false)
+        .with_metadata([("iox::column::type".to_string(), "iox::column_type::tag".to_string())].into());
+    let schema = Schema::new(vec![
+        Field::new("iox::measurement", DataType::Utf8, false),
+        tag,
+        Field::new("time", DataType::Timestamp(arrow_schema::TimeUnit::Nanosecond, None), false),
+        Field::new("usage", DataType::Float64, false),
+        Field::new("cores", DataType::Int32, false),
+        Field::new("idle", DataType::Int64, true),
+    ]);
+    RecordBatch::try_new(Arc::new(schema), vec![
+        Arc::new(StringArray::from(vec!["cpu", "cpu"])),
+        Arc::new(host.finish()),
+        Arc::new(TimestampNanosecondArray::from(vec![1, 2])),
+        Arc::new(Float64Array::from(vec![0.5, 1.5])),
+        Arc::new(Int32Array::from(vec![4, 8])),
+        Arc::new(Int64Array::from(vec![Some(7), None])),
+    ]).unwrap()
+}
+
+fn expected() -> Vec<Cpu> {
+    vec![
+        Cpu {
+            time: DateTime::from_timestamp_nanos(1),
+            host: "a".to_string(),
+            usage: 0.5,
+            core_count: 4,
+            idle: Some(7),
+            missing: 0,
+            note: String::new(),
+        },
+        Cpu {
+            time: DateTime::from_timestamp_nanos(2),
+            host: "b".to_string(),
+            usage: 1.5,
+            core_count: 8,
+            idle: None,
+            missing: 0,
+            note: String::new(),
+        },
+    ]
+}
+
+#[test]
+fn decodes_columns() {
+    assert_eq!(rows::<Cpu>(&cpu_batch()).unwrap(), expected());
+}
+
+#[test]
+fn from_point_types_decode_batches_a_row_at_a_time() {
+    let points = rows::<ViaPoint<Cpu>>(&cpu_batch()).unwrap();
+    assert_eq!(points.into_iter().map(ViaPoint::into_inner).collect::<Vec<_>>(), expected());
+}
+
+#[test]
+fn time_is_optional() {
+    let batch = RecordBatch::try_from_iter([("count", Arc::new(Int64Array::from(vec![3])) as ArrayRef)]).unwrap();
+    assert_eq!(rows::<Count>(&batch).unwrap(), vec![Count { count: 3 }]);
+}
+
+#[test]
+fn missing_and_null_required_fields_are_errors() {
+    let batch = RecordBatch::try_from_iter([("other", Arc::new(Int64Array::from(vec![3])) as ArrayRef)]).unwrap();
+    let result = rows::<Count>(&batch);
+    assert!(matches!(&result, Err(InfluxDBError::Other(m)) if m == "Missing required field: count"), "{result:?}");
+
+    let batch = RecordBatch::try_from_iter([("count", Arc::new(Int64Array::from(vec![None])) as ArrayRef)]).unwrap();
+    assert!(rows::<Count>(&batch).is_err());
+}
+
+#[test]
+fn mismatched_column_types_are_errors() {
+    let batch = RecordBatch::try_from_iter([("count", Arc::new(StringArray::from(vec!["3"])) as ArrayRef)]).unwrap();
+    let result = rows::<Count>(&batch);
+    assert!(matches!(&result, Err(InfluxDBError::Other(m)) if m.starts_with("Failed to convert field 'count'")), "{result:?}");
+}
+
+#[test]
+fn errors_stay_with_their_row() {
+    let batch = RecordBatch::try_from_iter([
+        ("small", Arc::new(UInt64Array::from(vec![Some(300), None, Some(5)])) as ArrayRef),
+        ("count", Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef),
+    ]).unwrap();
+    let pairs: Vec<_> = Pair::from_record_batch(&batch).into_iter().map(Result::ok).collect();
+    assert_eq!(pairs, [None, None, Some(Pair { small: 5, count: 3 })]);
+
+    // The first row has a null usage.
+    let batch = cpu_batch();
+    let mut columns = batch.columns().to_vec();
+    columns[3] = Arc::new(Float64Array::from(vec![None, Some(1.5)]));
+    let schema = batch.schema().as_ref().clone();
+    let fields: Vec<_> = schema.fields().iter().map(|f| f.as_ref().clone().with_nullable(true)).collect();
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap();
+    let rows = ViaPoint::<Cpu>::from_record_batch(&batch);
+    assert!(matches!(&rows[0], Err(InfluxDBError::Other(m)) if m.starts_with("Failed to convert field 'usage'")), "{rows:?}");
+    assert_eq!(rows[1].as_ref().unwrap().0.host, "b");
+}
@@ -1,4 +1,10 @@
-pub use influxdb3_core::{Point, ToPoint, FromPoint, TimestampPrecision, QueryType, InfluxDBError, Client, ClientBuilder};
+pub use influxdb3_core::{Point, ToPoint, FromPoint, FromRecordBatch, ViaPoint, DecodeColumn, RecordBatch, TimestampPrecision, QueryType, QueryOptions, CancellationToken, NonFiniteFloatPolicy, WriteOptions, InfluxDBError, PartialWriteError, LineError, ParseError, LineProtocolReader, RecordBatchStream, PointStream, ResultSchema, ColumnInfo, ColumnRole, FieldType, FieldKey, QueryBuilder, Aggregate, Order, quote_identifier, quote_literal, Client, ClientBuilder, FlightSqlClient, PreparedStatement, CommandGetDbSchemas, CommandGetTables, SqlInfo, WriteApi, WriteApiOptions, RetryPolicy, SpoolOptions, SpoolOverflow, DrainReport, export, ExportFormat, Csv, JsonLines};
+
+#[cfg(feature = "parquet")]
+pub use influxdb3_core::{Parquet, WriterProperties};
+
+#[cfg(feature = "table")]
+pub use influxdb3_core::Table;
 
 #[cfg(feature = "derive")]
-pub use influxdb3_macro::{ToPoint, FromPoint};
\ No newline at end of file
+pub use influxdb3_macro::{ToPoint, FromPoint, FromRecordBatch};
\ No newline at end of file